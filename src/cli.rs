@@ -0,0 +1,433 @@
+//! Headless command line mode, runs the same [Updater] stages as the [MainWindow](crate::MainWindow) without opening a window
+
+use og3_pack_updater::config::{Channel, Side, CONFIG_PATH};
+use og3_pack_updater::error::Result;
+use og3_pack_updater::progress::format_duration;
+use og3_pack_updater::release::Release;
+use og3_pack_updater::updater::close_modrinth_app;
+use og3_pack_updater::{
+    format_size, Config, Progress, ProgressInfo, UpdateError, UpdatePlan, Updater,
+};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// The pack is up to date, or `verify` found no differences from the latest version
+pub const EXIT_UP_TO_DATE: i32 = 0;
+/// Something went wrong
+pub const EXIT_FAILED: i32 = 1;
+/// An update was installed
+pub const EXIT_UPDATED: i32 = 2;
+/// `check` found an update
+pub const EXIT_UPDATE_AVAILABLE: i32 = 3;
+/// `verify` found files that are missing, modified or not part of the latest version of the pack
+pub const EXIT_VERIFY_MISMATCH: i32 = 4;
+
+const HELP: &str = "\
+OG3 Pack Updater
+
+Usage: OG3-Pack-Updater [COMMAND] [OPTIONS]
+
+Running without a command opens the updater window.
+
+Commands:
+  check     Check if a new version of the pack is available
+  update    Show what the latest version changes, then download and install it
+  install <file>
+            Install a .mrpack or pack zip you already have, e.g. without an internet connection
+  verify    Compare the installed files with the latest version without changing anything,
+            an out of date pack lists the files the update changes as well
+  restore   Go back to the version installed before the last update
+
+Options:
+  --config <path>          The config file to use [default: config.json]
+  --pack-location <dir>    The pack folder to update, creates the config if it does not exist
+  --side <client|server>   Install the files for a client or a server [default: from the config]
+  --channel <stable|beta>  Install only full releases or pre-releases as well [default: from the config]
+  --dry-run                Show what `update` or `install` would change without changing anything
+  -y, --yes                Install the update without asking for confirmation
+  -h, --help               Print this help
+
+--pack-location, --side and --channel are saved to the config when it is created and whenever
+update, install or restore changes the pack, so they only need to be passed once.
+
+Exit codes:
+  0  Up to date / nothing to change / restored
+  1  Failed
+  2  Updated
+  3  Update available (check, update --dry-run or update/install not confirmed)
+  4  Installed files differ from the latest version (verify)";
+
+/// The subcommand to run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Check,
+    Update,
+    Install,
+    Verify,
+    Restore,
+}
+
+/// The parsed command line arguments
+#[derive(Debug)]
+struct Args {
+    command: Command,
+    config: PathBuf,
+    pack_location: Option<PathBuf>,
+    side: Option<Side>,
+    channel: Option<Channel>,
+    /// The .mrpack or pack zip to install
+    file: Option<PathBuf>,
+    /// Only print the update plan
+    dry_run: bool,
+    /// Skip the confirmation before applying the update
+    yes: bool,
+}
+
+impl Args {
+    /// Parses the arguments, not including the program name
+    fn parse(args: Vec<String>) -> std::result::Result<Self, String> {
+        let mut command = None;
+        let mut config = PathBuf::from(CONFIG_PATH);
+        let mut pack_location = None;
+        let mut side = None;
+        let mut channel = None;
+        let mut file = None;
+        let mut dry_run = false;
+        let mut yes = false;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "check" => command = Some(Command::Check),
+                "update" => command = Some(Command::Update),
+                "install" => {
+                    command = Some(Command::Install);
+                    file = Some(
+                        args.next()
+                            .map(PathBuf::from)
+                            .ok_or("install needs a .mrpack or pack zip")?,
+                    )
+                }
+                "verify" => command = Some(Command::Verify),
+                "restore" => command = Some(Command::Restore),
+                "--config" => {
+                    config = args
+                        .next()
+                        .map(PathBuf::from)
+                        .ok_or("--config needs a path")?
+                }
+                "--pack-location" => {
+                    pack_location = Some(
+                        args.next()
+                            .map(PathBuf::from)
+                            .ok_or("--pack-location needs a directory")?,
+                    )
+                }
+                "--side" => {
+                    side = Some(
+                        args.next()
+                            .ok_or("--side needs client or server")?
+                            .parse()?,
+                    )
+                }
+                "--channel" => {
+                    channel = Some(
+                        args.next()
+                            .ok_or("--channel needs stable or beta")?
+                            .parse()?,
+                    )
+                }
+                "--dry-run" => dry_run = true,
+                "-y" | "--yes" => yes = true,
+                other => return Err(format!("unknown argument '{}'", other)),
+            }
+        }
+
+        Ok(Self {
+            command: command.ok_or("no command given")?,
+            config,
+            pack_location,
+            side,
+            channel,
+            file,
+            dry_run,
+            yes,
+        })
+    }
+}
+
+/// Prints [Progress] from the [Updater] to the terminal
+struct TerminalProgress {
+    /// The last printed percentage, so each step is only printed once
+    last: AtomicU32,
+}
+
+impl Progress for TerminalProgress {
+    fn update(&self, info: &ProgressInfo) {
+        let percent = (info.fraction * 100.0).round() as u32;
+        if self.last.swap(percent, Ordering::Relaxed) == percent {
+            return;
+        }
+
+        let mut line = format!("[{:>3}%] {}", percent, info.phase.name());
+        if !info.current_file.is_empty() {
+            line.push_str(&format!(" {}", info.current_file));
+        }
+        if let Some(speed) = info.bytes_per_second {
+            line.push_str(&format!(" ({}/s", format_size(speed as u64)));
+            if let Some(eta) = info.eta {
+                line.push_str(&format!(", {} left", format_duration(eta)));
+            }
+            line.push(')');
+        }
+        println!("{}", line);
+    }
+}
+
+/// Returns true if the arguments ask for help
+pub fn wants_help(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == "-h" || arg == "--help")
+}
+
+/// Runs the command line interface and returns the exit code
+pub fn run(args: Vec<String>) -> i32 {
+    if wants_help(&args) {
+        println!("{}", HELP);
+        return EXIT_UP_TO_DATE;
+    }
+
+    let args = match Args::parse(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, HELP);
+            return EXIT_FAILED;
+        }
+    };
+
+    match run_command(&args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            EXIT_FAILED
+        }
+    }
+}
+
+/// Runs a parsed [Command]
+fn run_command(args: &Args) -> Result<i32> {
+    let mut config = load_config(args)?;
+    if args.command == Command::Verify {
+        // always compare hashes when verifying
+        config.redownload_all = false;
+    }
+    let mut updater = Updater::new(config, &args.config);
+
+    if args.command == Command::Restore {
+        close_modrinth_app();
+
+        let version = updater.restore_previous()?;
+        println!("Restored {}", version);
+        return Ok(EXIT_UP_TO_DATE);
+    }
+
+    if let (Command::Install, Some(file)) = (args.command, &args.file) {
+        println!(
+            "Installing {} over {}",
+            file.display(),
+            updater.config().version
+        );
+
+        let progress = TerminalProgress {
+            last: AtomicU32::new(0),
+        };
+        let plan = updater.plan_local(file, &progress)?;
+
+        let offline_warning = plan.offline_warning();
+        if !offline_warning.is_empty() {
+            println!("Warning: {}", offline_warning);
+        }
+
+        return confirm_and_apply(args, &mut updater, plan, &progress);
+    }
+
+    let check = updater.check()?;
+
+    let code = match args.command {
+        Command::Check => {
+            if check.update_available() {
+                println!(
+                    "Update available: {} -> {}",
+                    check.installed_version,
+                    release_name(&check.release)
+                );
+                EXIT_UPDATE_AVAILABLE
+            } else {
+                println!("Pack is up to date! ({})", check.installed_version);
+                EXIT_UP_TO_DATE
+            }
+        }
+        Command::Update => {
+            if !check.update_available() {
+                println!("Pack is up to date! ({})", check.installed_version);
+                return Ok(EXIT_UP_TO_DATE);
+            }
+
+            println!(
+                "Updating {} -> {}",
+                check.installed_version,
+                release_name(&check.release)
+            );
+
+            let progress = TerminalProgress {
+                last: AtomicU32::new(0),
+            };
+            let plan = updater.plan(&check.release, &progress)?;
+
+            confirm_and_apply(args, &mut updater, plan, &progress)?
+        }
+        Command::Verify => {
+            if check.update_available() {
+                println!(
+                    "Note: {} is installed but {} is the latest, the files it changes are listed as well",
+                    check.installed_version, check.release.tag_name
+                );
+            }
+
+            let plan = updater.plan(&check.release, &())?;
+            print_differences(&plan);
+
+            if plan.to_delete.is_empty() && plan.to_download.is_empty() {
+                println!("All files match the latest version {}", plan.new_version);
+                EXIT_UP_TO_DATE
+            } else {
+                EXIT_VERIFY_MISMATCH
+            }
+        }
+        Command::Restore | Command::Install => {
+            unreachable!("restore and install are handled before checking for updates")
+        }
+    };
+
+    Ok(code)
+}
+
+/// Prints an [UpdatePlan], asks the user to confirm it unless `--yes` was passed and applies it
+fn confirm_and_apply(
+    args: &Args,
+    updater: &mut Updater,
+    plan: UpdatePlan,
+    progress: &TerminalProgress,
+) -> Result<i32> {
+    for line in plan.describe() {
+        println!("  {}", line);
+    }
+    println!("{}", plan.summary());
+
+    for warning in plan.version_warnings() {
+        println!("Warning: {}", warning);
+    }
+
+    for file in plan.optional.iter().filter(|file| file.new) {
+        println!(
+            "New optional file {} ({}) will be installed, set it to false in optional_files in {} to skip it",
+            file.path,
+            format_size(file.size),
+            args.config.display()
+        );
+    }
+
+    if args.dry_run {
+        return Ok(EXIT_UPDATE_AVAILABLE);
+    }
+    if !args.yes && !confirm("Apply this update?") {
+        println!("Update cancelled, nothing was changed");
+        return Ok(EXIT_UPDATE_AVAILABLE);
+    }
+
+    // save the defaults so the new optional files are not reported as new again next time
+    let defaults: Vec<(String, bool)> = plan
+        .optional
+        .iter()
+        .filter(|file| file.new)
+        .map(|file| (file.path.clone(), file.install))
+        .collect();
+    if !defaults.is_empty() {
+        updater.choose_optional(defaults)?;
+    }
+
+    close_modrinth_app();
+
+    updater.apply(plan, progress)?;
+
+    println!("Update Installed!");
+    Ok(EXIT_UPDATED)
+}
+
+/// Loads the config file, or creates it from `--pack-location` if it does not exist
+///
+/// `--pack-location`, `--side` and `--channel` replace the values in the config, they are saved
+/// along with the rest of the config whenever the [Updater] changes the pack
+fn load_config(args: &Args) -> Result<Config> {
+    if !Path::new(&args.config).exists() {
+        let pack_location = args.pack_location.clone().ok_or_else(|| {
+            UpdateError::Config(format!(
+                "{} not found, pass --pack-location to create one",
+                args.config.display()
+            ))
+        })?;
+        let mut config = Config::from_pack_location(pack_location);
+        if let Some(side) = args.side {
+            config.side = side;
+        }
+        if let Some(channel) = args.channel {
+            config.channel = channel;
+        }
+        config.save(&args.config)?;
+        println!("Created {}", args.config.display());
+        return Ok(config);
+    }
+
+    let mut config = Config::load(&args.config)?;
+    if let Some(pack_location) = &args.pack_location {
+        config.pack_location = pack_location.clone();
+    }
+    if let Some(side) = args.side {
+        config.side = side;
+    }
+    if let Some(channel) = args.channel {
+        config.channel = channel;
+    }
+    Ok(config)
+}
+
+/// The tag of a [Release], marked if it is a pre-release
+fn release_name(release: &Release) -> String {
+    if release.prerelease {
+        format!("{} (pre-release)", release.tag_name)
+    } else {
+        release.tag_name.clone()
+    }
+}
+
+/// Prints what an [UpdatePlan] would change
+fn print_differences(plan: &UpdatePlan) {
+    for file in &plan.to_delete {
+        println!("not in pack or modified: {}", file.name);
+    }
+    for file in &plan.to_download {
+        println!("missing or modified: {}", file.name);
+    }
+}
+
+/// Asks a yes or no question on the terminal, anything but yes counts as no
+fn confirm(question: &str) -> bool {
+    print!("{} [y/N] ", question);
+    let _ = std::io::stdout().flush();
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
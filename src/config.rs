@@ -0,0 +1,169 @@
+use crate::error::{PathContext, Result, UpdateError};
+use crate::source::SourceConfig;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// The default location of the config file, relative to the working directory
+pub const CONFIG_PATH: &str = "config.json";
+
+/// Which side of the game the pack is installed for, files the index marks as unsupported on it are skipped
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    #[default]
+    Client,
+    Server,
+}
+
+impl std::str::FromStr for Side {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "client" => Ok(Side::Client),
+            "server" => Ok(Side::Server),
+            other => Err(format!("'{}' is not client or server", other)),
+        }
+    }
+}
+
+/// Which releases of the pack are installed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    /// Only full releases
+    #[default]
+    Stable,
+    /// Pre-releases as well, for testers
+    Beta,
+}
+
+impl Channel {
+    /// The name of the channel for the user
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Beta => "beta",
+        }
+    }
+}
+
+impl std::str::FromStr for Channel {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "stable" => Ok(Channel::Stable),
+            "beta" => Ok(Channel::Beta),
+            other => Err(format!("'{}' is not stable or beta", other)),
+        }
+    }
+}
+
+/// The main config [Struct] for the updater
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The location of the installed pack
+    pub pack_location: PathBuf,
+    /// The version of the installed pack
+    pub version: String,
+    /// If all files should be installed regardless (no hash rate limiting)
+    pub redownload_all: bool,
+    /// How many snapshots of previous versions to keep, 0 turns snapshots off
+    #[serde(default = "default_snapshots_to_keep")]
+    pub snapshots_to_keep: usize,
+    /// How many mods are downloaded at the same time
+    #[serde(default = "default_max_concurrent_downloads")]
+    pub max_concurrent_downloads: usize,
+    /// If the pack is a client or a server install
+    #[serde(default)]
+    pub side: Side,
+    /// If pre-releases are installed as well as full releases
+    #[serde(default)]
+    pub channel: Channel,
+    /// Where new versions of the pack are checked for
+    #[serde(default)]
+    pub source: SourceConfig,
+    /// Sources tried in order when [Config::source] can not be reached, e.g. a mirror on the pack's site
+    #[serde(default)]
+    pub fallback_sources: Vec<SourceConfig>,
+    /// Hosts files may be downloaded from as well as the ones the Modrinth format allows, e.g. "og3.infy.uk"
+    #[serde(default)]
+    pub trusted_hosts: Vec<String>,
+    /// The release tag of the installed version by the name of the source it came from
+    ///
+    /// Compared with the source's latest release, as tags like "v1.3-beta1" can not be worked out from the version
+    #[serde(default)]
+    pub installed_tags: BTreeMap<String, String>,
+    /// The Minecraft and loader versions the installed version of the pack needs, from its index `dependencies`
+    #[serde(default)]
+    pub installed_dependencies: BTreeMap<String, String>,
+    /// The paths of the index files the installed version of the pack put in place
+    ///
+    /// Paths missing from the next version's index are deleted, so files outside the mods folder are not left behind
+    #[serde(default)]
+    pub installed_files: Vec<String>,
+    /// The user's choice for each optional file in the index by its path, true to install it
+    ///
+    /// Optional files without a choice are installed
+    #[serde(default)]
+    pub optional_files: BTreeMap<String, bool>,
+}
+
+fn default_snapshots_to_keep() -> usize {
+    3
+}
+
+fn default_max_concurrent_downloads() -> usize {
+    8
+}
+
+impl Config {
+    /// Creates a new [Config] for a pack folder selected by the user
+    ///
+    /// The version is taken from the end of the folder name as Modrinth names profiles "<name> <version>"
+    pub fn from_pack_location(pack_location: PathBuf) -> Self {
+        let temp_string = pack_location.display().to_string();
+
+        // this should be the version unless if someone did something stupid
+        let version = temp_string.split(' ').next_back().unwrap().to_string();
+
+        Self {
+            pack_location,
+            version,
+            redownload_all: false,
+            snapshots_to_keep: default_snapshots_to_keep(),
+            max_concurrent_downloads: default_max_concurrent_downloads(),
+            side: Side::default(),
+            channel: Channel::default(),
+            source: SourceConfig::default(),
+            fallback_sources: Vec::new(),
+            trusted_hosts: Vec::new(),
+            installed_tags: BTreeMap::new(),
+            installed_dependencies: BTreeMap::new(),
+            installed_files: Vec::new(),
+            optional_files: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the [Config] from a json file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path).with_path(path)?;
+        let reader = BufReader::new(file);
+        serde_json::from_reader(reader)
+            .map_err(|e| UpdateError::Config(format!("{} is invalid: {}", path.display(), e)))
+    }
+
+    /// Writes the [Config] to a json file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let json =
+            serde_json::to_string_pretty(self).map_err(|e| UpdateError::Config(e.to_string()))?;
+        fs::write(&path, json).with_path(path)
+    }
+}
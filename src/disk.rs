@@ -0,0 +1,63 @@
+use crate::error::{PathContext, Result, UpdateError};
+use std::path::Path;
+
+/// The free space in bytes on the drive holding `path`, the nearest folder that exists is used if `path` does not
+pub fn available_space(path: &Path) -> Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .unwrap_or(Path::new("."));
+    free_bytes(existing).with_path(existing)
+}
+
+/// Checks the drive holding `path` has at least `needed` bytes free
+pub fn check_space(path: &Path, needed: u64) -> Result<()> {
+    let available = available_space(path)?;
+    if available < needed {
+        return Err(UpdateError::NotEnoughSpace {
+            path: path.to_path_buf(),
+            needed,
+            available,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn free_bytes(path: &Path) -> std::io::Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use winapi::um::fileapi::GetDiskFreeSpaceExW;
+    use winapi::um::winnt::ULARGE_INTEGER;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    unsafe {
+        let mut available: ULARGE_INTEGER = std::mem::zeroed();
+        if GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        ) == 0
+        {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(*available.QuadPart())
+    }
+}
+
+#[cfg(unix)]
+fn free_bytes(path: &Path) -> std::io::Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    unsafe {
+        let mut stat: libc::statvfs = std::mem::zeroed();
+        if libc::statvfs(c_path.as_ptr(), &mut stat) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        #[allow(clippy::unnecessary_cast)]
+        Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+}
@@ -0,0 +1,281 @@
+use crate::error::{PathContext, Result, UpdateError};
+use crate::hash::{find_mismatch, Hasher};
+use crate::FileHash;
+use crate::USER_AGENT;
+use reqwest::blocking::Response;
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::StatusCode;
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The hosts the Modrinth format allows files to be downloaded from
+pub const ALLOWED_HOSTS: [&str; 4] = [
+    "cdn.modrinth.com",
+    "github.com",
+    "raw.githubusercontent.com",
+    "gitlab.com",
+];
+
+/// Why `url` may not be downloaded from, `None` if it is https and on one of the [ALLOWED_HOSTS]
+/// or the `trusted_hosts` from the [Config](crate::Config)
+pub fn blocked_reason(url: &str, trusted_hosts: &[String]) -> Option<&'static str> {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return Some("not a valid URL");
+    };
+    if url.scheme() != "https" {
+        return Some("not https");
+    }
+    let Some(host) = url.host_str() else {
+        return Some("no host");
+    };
+
+    let trusted = ALLOWED_HOSTS
+        .iter()
+        .copied()
+        .chain(trusted_hosts.iter().map(|h| h.as_str()))
+        .any(|allowed| allowed.eq_ignore_ascii_case(host));
+    (!trusted).then_some("untrusted host, add it to trusted_hosts in the config if you trust it")
+}
+
+/// What a finished download has to match before it is moved into place
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Expected<'a> {
+    /// The hashes of the file, every one of them has to match
+    pub hashes: &'a [FileHash],
+    /// The size of the file in bytes
+    pub size: Option<u64>,
+}
+
+/// The `.part` file a download to `path` is written to until it is complete
+pub fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// The file next to a `part` file holding the ETag or Last-Modified date of the download, so a changed file is not resumed
+fn validator_path(part: &Path) -> PathBuf {
+    let mut name = part.file_name().unwrap_or_default().to_os_string();
+    name.push(".validator");
+    part.with_file_name(name)
+}
+
+/// Deletes a `part` file and its validator so the next attempt starts from scratch
+fn discard_part(part: &Path) {
+    let _ = fs::remove_file(part);
+    let _ = fs::remove_file(validator_path(part));
+}
+
+/// Downloads `url` to `dest`, going through the `part` file so an interrupted download can be resumed
+///
+/// If `part` already exists only the rest of the file is requested with an HTTP Range request,
+/// sent with If-Range so the server sends the whole file again if it changed. A reply that does not
+/// continue the part file where it ends starts the download again.
+/// The file is only moved to `dest` once it is complete and matches `expected`,
+/// a file that does not match is deleted so the next attempt starts from scratch.
+/// `on_bytes` is called with the number of bytes each time some are written.
+pub fn download_resumable(
+    client: &reqwest::blocking::Client,
+    url: &str,
+    part: &Path,
+    dest: &Path,
+    expected: Expected,
+    cancelled: &AtomicBool,
+    on_bytes: &dyn Fn(u64),
+) -> Result<()> {
+    if let Some(parent) = part.parent() {
+        fs::create_dir_all(parent).with_path(parent)?;
+    }
+
+    // hash what we already have so the hash covers the whole file
+    let mut hasher = Hasher::new();
+    let mut existing = 0;
+    if part.exists() {
+        let mut file = File::open(part).with_path(part)?;
+        let mut buffer = [0u8; 8192];
+        loop {
+            let count = file.read(&mut buffer).with_path(part)?;
+            if count == 0 {
+                break;
+            }
+            hasher.update(&buffer[..count]);
+            existing += count as u64;
+        }
+    }
+
+    let mut request = client.get(url).header("User-Agent", USER_AGENT);
+    if existing > 0 {
+        println!("Resuming {} from {} bytes", url, existing);
+        request = request.header(RANGE, format!("bytes={}-", existing));
+        if let Ok(validator) = fs::read_to_string(validator_path(part)) {
+            request = request.header(IF_RANGE, validator);
+        }
+    }
+    let mut resp = request.send()?;
+
+    if resp.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // the part file is no use to the server, start again next time
+        discard_part(part);
+        return Err(UpdateError::Download(format!(
+            "{} could not be resumed",
+            url
+        )));
+    }
+    resp = resp.error_for_status()?;
+
+    let resumed = existing > 0 && resp.status() == StatusCode::PARTIAL_CONTENT;
+    if resumed && !continues_part(&resp, existing, expected.size) {
+        // the part file is from another version of the file
+        println!(
+            "{} changed since it was partly downloaded, starting again",
+            url
+        );
+        drop(resp);
+        discard_part(part);
+        return download_resumable(client, url, part, dest, expected, cancelled, on_bytes);
+    }
+
+    let mut out_file = if resumed {
+        on_bytes(existing);
+        OpenOptions::new().append(true).open(part).with_path(part)?
+    } else {
+        // the server sent the whole file
+        hasher = Hasher::new();
+        existing = 0;
+        save_validator(part, &resp)?;
+        File::create(part).with_path(part)?
+    };
+
+    let mut written = existing;
+    let mut buffer = [0u8; 8192];
+    loop {
+        // the part file is kept so the download can be resumed
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(UpdateError::Cancelled);
+        }
+
+        let count = resp
+            .read(&mut buffer)
+            .map_err(|e| UpdateError::Download(format!("connection lost: {}", e)))?;
+        if count == 0 {
+            break;
+        }
+        hasher.update(&buffer[..count]);
+        out_file.write_all(&buffer[..count]).with_path(part)?;
+        written += count as u64;
+        on_bytes(count as u64);
+    }
+    drop(out_file);
+
+    if let Some(size) = expected.size {
+        if size != written {
+            discard_part(part);
+            return Err(UpdateError::Download(format!(
+                "expected {} bytes but got {}",
+                size, written
+            )));
+        }
+    }
+    let actual = hasher.finalize();
+    if let Some(hash) = find_mismatch(expected.hashes, &actual) {
+        discard_part(part);
+        let got = actual
+            .iter()
+            .find(|have| have.algorithm() == hash.algorithm())
+            .map(|have| have.value())
+            .unwrap_or_default();
+        return Err(UpdateError::Download(format!(
+            "{} mismatch, expected {} but got {}",
+            hash.algorithm(),
+            hash.value(),
+            got
+        )));
+    }
+
+    move_file(part, dest)?;
+    let _ = fs::remove_file(validator_path(part));
+    Ok(())
+}
+
+/// If the Content-Range of a 206 reply starts where the part file ends and is for a file of the `expected` size
+fn continues_part(resp: &Response, existing: u64, expected: Option<u64>) -> bool {
+    let Some(range) = resp
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    // e.g. "bytes 4-13/14"
+    let Some((start, total)) = range
+        .strip_prefix("bytes ")
+        .and_then(|range| range.split_once('/'))
+        .and_then(|(span, total)| Some((span.split_once('-')?.0, total)))
+    else {
+        return false;
+    };
+
+    start.parse() == Ok(existing) && expected.is_none_or(|size| total.parse() == Ok(size))
+}
+
+/// Saves the strong ETag or the Last-Modified date of a download next to its `part` file, to resume it with If-Range
+fn save_validator(part: &Path, resp: &Response) -> Result<()> {
+    let header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    // weak ETags can not be used with If-Range
+    let etag = header(ETAG).filter(|etag| !etag.starts_with("W/"));
+    let path = validator_path(part);
+    match etag.or_else(|| header(LAST_MODIFIED)) {
+        Some(validator) => fs::write(&path, validator).with_path(&path),
+        None => {
+            let _ = fs::remove_file(&path);
+            Ok(())
+        }
+    }
+}
+
+/// Moves a finished download into place, copying it if `dest` is on another drive
+fn move_file(from: &Path, dest: &Path) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).with_path(parent)?;
+    }
+    if fs::rename(from, dest).is_err() {
+        // the old file may be hard linked with the pack, so remove it instead of writing over it
+        if dest.exists() {
+            fs::remove_file(dest).with_path(dest)?;
+        }
+        fs::copy(from, dest).with_path(dest)?;
+        fs::remove_file(from).with_path(from)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_https_urls_on_trusted_hosts_are_allowed() {
+        let trusted = ["og3.infy.uk".to_string()];
+
+        assert!(blocked_reason("https://cdn.modrinth.com/a.jar", &[]).is_none());
+        assert!(blocked_reason("https://OG3.infy.uk/a.jar", &trusted).is_none());
+        assert_eq!(
+            blocked_reason("http://cdn.modrinth.com/a.jar", &[]),
+            Some("not https")
+        );
+        assert_eq!(
+            blocked_reason("http://og3.infy.uk/a.jar", &trusted),
+            Some("not https")
+        );
+        assert!(blocked_reason("https://og3.infy.uk/a.jar", &[])
+            .is_some_and(|reason| reason.contains("trusted_hosts")));
+        assert_eq!(blocked_reason("a.jar", &[]), Some("not a valid URL"));
+    }
+}
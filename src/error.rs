@@ -0,0 +1,116 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Shorthand for a [std::result::Result] with an [UpdateError]
+pub type Result<T> = std::result::Result<T, UpdateError>;
+
+/// Everything that can go wrong while updating the pack
+#[derive(Debug)]
+pub enum UpdateError {
+    /// A request to github or a mod host failed
+    Network(reqwest::Error),
+    /// The release does not contain what the updater needs
+    Release(String),
+    /// The pack zip or .mrpack could not be read
+    Archive(String),
+    /// The modrinth.index.json is missing or invalid
+    Index(String),
+    /// A downloaded file is incomplete or does not match the index
+    Download(String),
+    /// A file or folder could not be read or written
+    Filesystem {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The config file is missing or invalid
+    Config(String),
+    /// A snapshot could not be taken or restored
+    Snapshot(String),
+    /// Files in the index would be downloaded from URLs that are not https or on hosts that are not trusted, with the reason for each
+    BlockedDownloads(Vec<(String, String)>),
+    /// An entry in the .mrpack would be written outside the pack or somewhere it should not be
+    UnsafePath { entry: String, reason: String },
+    /// There is not enough free space on a drive to install the update
+    NotEnoughSpace {
+        path: PathBuf,
+        needed: u64,
+        available: u64,
+    },
+    /// The update was cancelled before it was swapped into the pack
+    Cancelled,
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::Network(e) => write!(f, "Network error: {}", e),
+            UpdateError::Release(e) => write!(f, "Release error: {}", e),
+            UpdateError::Archive(e) => write!(f, "Could not read the pack: {}", e),
+            UpdateError::Index(e) => write!(f, "Invalid modrinth.index.json: {}", e),
+            UpdateError::Download(e) => write!(f, "Download failed: {}", e),
+            UpdateError::Filesystem { path, source } => {
+                write!(f, "Could not access {}: {}", path.display(), source)
+            }
+            UpdateError::Config(e) => write!(f, "Config error: {}", e),
+            UpdateError::Snapshot(e) => write!(f, "Snapshot error: {}", e),
+            UpdateError::BlockedDownloads(urls) => {
+                write!(f, "Refusing to download some files of the update:")?;
+                for (url, reason) in urls {
+                    write!(f, "\n{} ({})", url, reason)?;
+                }
+                Ok(())
+            }
+            UpdateError::UnsafePath { entry, reason } => {
+                write!(f, "Refusing to install {}: {}", entry, reason)
+            }
+            UpdateError::NotEnoughSpace {
+                path,
+                needed,
+                available,
+            } => write!(
+                f,
+                "Not enough disk space for {}: the update needs {} but only {} is free",
+                path.display(),
+                crate::format_size(*needed),
+                crate::format_size(*available)
+            ),
+            UpdateError::Cancelled => write!(f, "The update was cancelled, nothing was changed"),
+        }
+    }
+}
+
+impl std::error::Error for UpdateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UpdateError::Network(e) => Some(e),
+            UpdateError::Filesystem { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for UpdateError {
+    fn from(e: reqwest::Error) -> Self {
+        UpdateError::Network(e)
+    }
+}
+
+impl From<zip::result::ZipError> for UpdateError {
+    fn from(e: zip::result::ZipError) -> Self {
+        UpdateError::Archive(e.to_string())
+    }
+}
+
+/// Adds the [Path] that was being accessed to an [std::io::Error]
+pub(crate) trait PathContext<T> {
+    fn with_path<P: AsRef<Path>>(self, path: P) -> Result<T>;
+}
+
+impl<T> PathContext<T> for std::io::Result<T> {
+    fn with_path<P: AsRef<Path>>(self, path: P) -> Result<T> {
+        self.map_err(|source| UpdateError::Filesystem {
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+}
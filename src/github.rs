@@ -0,0 +1,96 @@
+use crate::config::Channel;
+use crate::error::{Result, UpdateError};
+use crate::release::Release;
+use crate::source::ReleaseSource;
+use crate::USER_AGENT;
+use serde::Deserialize;
+
+/// The GitHub API
+pub const API_URL: &str = "https://api.github.com";
+
+/// A release from the GitHub API list of releases, with the fields needed to pick one
+#[derive(Deserialize)]
+struct ListedRelease {
+    #[serde(default)]
+    draft: bool,
+    /// When the release was published, drafts have not been
+    #[serde(default)]
+    published_at: Option<String>,
+    #[serde(flatten)]
+    release: Release,
+}
+
+/// Gets releases of the pack from a GitHub repository, the pack zip is attached to each release
+pub struct GitHubSource {
+    owner: String,
+    repo: String,
+    api_url: String,
+    channel: Channel,
+}
+
+impl GitHubSource {
+    /// Creates a source for the repository `owner`/`repo`, using the API at `api_url`
+    pub fn new(owner: &str, repo: &str, api_url: &str) -> Self {
+        Self {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            channel: Channel::default(),
+        }
+    }
+
+    /// Finds the newest release on `channel`, pre-releases are only used on [Channel::Beta]
+    pub fn with_channel(mut self, channel: Channel) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// The URL of the repository's API
+    fn repo_url(&self) -> String {
+        format!("{}/repos/{}/{}", self.api_url, self.owner, self.repo)
+    }
+}
+
+impl ReleaseSource for GitHubSource {
+    fn name(&self) -> String {
+        format!("GitHub {}/{}", self.owner, self.repo)
+    }
+
+    fn latest(&self, client: &reqwest::blocking::Client) -> Result<Release> {
+        if self.channel == Channel::Stable {
+            // the latest release is never a pre-release
+            return Ok(client
+                .get(format!("{}/releases/latest", self.repo_url()))
+                .header("User-Agent", USER_AGENT)
+                .send()?
+                .error_for_status()?
+                .json()?);
+        }
+
+        let releases: Vec<ListedRelease> = client
+            .get(format!("{}/releases", self.repo_url()))
+            .header("User-Agent", USER_AGENT)
+            .send()?
+            .error_for_status()?
+            .json()?;
+
+        releases
+            .into_iter()
+            .filter(|listed| !listed.draft)
+            .max_by(|a, b| a.published_at.cmp(&b.published_at))
+            .map(|listed| listed.release)
+            .ok_or_else(|| UpdateError::Release(format!("{} has no releases", self.name())))
+    }
+
+    /// Releases are tagged with the major and minor version e.g. "v1.2"
+    fn installed_tag(&self, installed_version: &str) -> Result<String> {
+        let mut parts = installed_version.split('.');
+        match (parts.next(), parts.next()) {
+            (Some(major), Some(minor)) => Ok(format!("v{}.{}", major, minor)),
+            _ => Err(UpdateError::Config(format!(
+                "the installed version '{}' is not a valid pack version",
+                installed_version
+            ))),
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use crate::progress::Tracker;
+use crate::FileInfo;
+use sha1::Sha1;
+use sha2::Digest;
+use sha2::Sha512;
+use std::fs;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+
+/// A hash of a file from the index or computed locally
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FileHash {
+    Sha512(String),
+    Sha1(String),
+}
+
+impl FileHash {
+    /// The name of the algorithm as the index writes it
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            FileHash::Sha512(_) => "sha512",
+            FileHash::Sha1(_) => "sha1",
+        }
+    }
+
+    /// The hash as lowercase hex
+    pub fn value(&self) -> &str {
+        match self {
+            FileHash::Sha512(value) | FileHash::Sha1(value) => value,
+        }
+    }
+}
+
+/// Works out every supported [FileHash] of some data in one pass
+#[derive(Clone, Default)]
+pub struct Hasher {
+    sha512: Sha512,
+    sha1: Sha1,
+}
+
+impl Hasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.sha512.update(data);
+        self.sha1.update(data);
+    }
+
+    /// The hashes of the data, strongest first
+    pub fn finalize(self) -> Vec<FileHash> {
+        vec![
+            FileHash::Sha512(format!("{:x}", self.sha512.finalize())),
+            FileHash::Sha1(format!("{:x}", self.sha1.finalize())),
+        ]
+    }
+}
+
+/// Checks `actual` hashes against every hash in `expected`, an algorithm only in `expected` counts as a mismatch
+///
+/// Returns the first expected hash that does not match
+pub fn find_mismatch<'a>(expected: &'a [FileHash], actual: &[FileHash]) -> Option<&'a FileHash> {
+    expected.iter().find(|hash| {
+        !actual.iter().any(|have| {
+            have.algorithm() == hash.algorithm() && have.value().eq_ignore_ascii_case(hash.value())
+        })
+    })
+}
+
+/// Whether `actual` has the strongest hash in `expected`, weaker hashes are not compared
+pub fn matches_strongest(expected: &[FileHash], actual: &[FileHash]) -> bool {
+    expected
+        .first()
+        .is_some_and(|hash| find_mismatch(std::slice::from_ref(hash), actual).is_none())
+}
+
+/// Computes every [FileHash] of a file at a [Path]
+pub fn compute_hashes_for_file<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<FileHash>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut hasher = Hasher::new();
+    let mut buffer = [0u8; 8192];
+
+    loop {
+        let count = file.read(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+        hasher.update(&buffer[..count]);
+    }
+
+    Ok(hasher.finalize())
+}
+
+/// Computes hashes using [compute_hashes_for_file] for all files in a [PathBuf] that is a directory
+///
+/// Each hashed file is counted on the [Tracker]
+pub fn get_all_files_with_hashes(
+    root_dir: PathBuf,
+    tracker: &Tracker,
+) -> Result<Vec<FileInfo>, std::io::Error> {
+    let mut handles = Vec::new();
+    let (tx, rx) = mpsc::channel();
+
+    for entry_result in fs::read_dir(root_dir)? {
+        let entry = entry_result?;
+        let path = entry.path();
+
+        if path.is_file() {
+            let tx = tx.clone();
+            // a name that is not UTF-8 is still listed, so the file is not skipped
+            let name = entry.file_name().to_string_lossy().to_string();
+            handles.push(thread::spawn(move || {
+                if let Ok(hashes) = compute_hashes_for_file(&path) {
+                    let size = fs::metadata(&path).map(|m| m.len()).ok();
+                    let _ = tx.send(Some(FileInfo::new(name, Vec::new(), hashes, size)));
+                } else {
+                    let _ = tx.send(None);
+                }
+            }));
+        }
+    }
+
+    drop(tx);
+
+    tracker.set_total(handles.len() as u64);
+
+    let result: Vec<FileInfo> = rx
+        .into_iter()
+        .inspect(|file_info| {
+            if let Some(file_info) = file_info {
+                tracker.file(&file_info.name);
+            }
+            tracker.advance(1);
+        })
+        .flatten()
+        .collect();
+
+    for handle in handles {
+        let _ = handle.join();
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sha512(value: &str) -> FileHash {
+        FileHash::Sha512(value.to_string())
+    }
+
+    fn sha1(value: &str) -> FileHash {
+        FileHash::Sha1(value.to_string())
+    }
+
+    #[test]
+    fn hasher_gives_sha512_first() {
+        let mut hasher = Hasher::new();
+        hasher.update(b"abc");
+        assert_eq!(
+            hasher.finalize(),
+            vec![
+                sha512("ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"),
+                sha1("a9993e364706816aba3e25717850c26c9cd0d89d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn find_mismatch_checks_every_expected_hash() {
+        let actual = [sha512("aa"), sha1("bb")];
+
+        assert_eq!(find_mismatch(&[sha512("AA"), sha1("bb")], &actual), None);
+        assert_eq!(
+            find_mismatch(&[sha512("aa"), sha1("cc")], &actual),
+            Some(&sha1("cc"))
+        );
+        // the same value under another algorithm is not a match
+        assert_eq!(
+            find_mismatch(&[sha1("aa")], &[sha512("aa")]),
+            Some(&sha1("aa"))
+        );
+        assert_eq!(find_mismatch(&[sha1("aa")], &[]), Some(&sha1("aa")));
+    }
+
+    #[test]
+    fn matches_strongest_prefers_sha512() {
+        let actual = [sha512("aa"), sha1("bb")];
+
+        // a wrong sha1 does not matter when the sha512 matches
+        assert!(matches_strongest(&[sha512("aa"), sha1("cc")], &actual));
+        assert!(!matches_strongest(&[sha512("cc"), sha1("bb")], &actual));
+        assert!(matches_strongest(&[sha1("BB")], &actual));
+        assert!(!matches_strongest(&[], &actual));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::config::Side;
+use crate::error::{Result, UpdateError};
+use crate::paths::safe_relative_path;
+use crate::{FileHash, FileInfo};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// The only `formatVersion` of modrinth.index.json there is
+const FORMAT_VERSION: u32 = 1;
+
+/// The modrinth.index.json file at the root of a .mrpack
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MrpackIndex {
+    /// The version of the index format, always 1
+    pub format_version: u32,
+    /// The game the pack is for, always "minecraft"
+    pub game: String,
+    /// The version of the pack, the updater stores this in the [Config](crate::Config)
+    pub version_id: String,
+    /// The name of the pack
+    pub name: String,
+    /// A short description of the pack
+    #[serde(default)]
+    pub summary: Option<String>,
+    /// The files that are downloaded into the pack
+    pub files: Vec<MrpackFile>,
+    /// The Minecraft and mod loader versions the pack needs, e.g. "minecraft" -> "1.20.1"
+    pub dependencies: HashMap<String, String>,
+}
+
+/// A file in the `files` list of a [MrpackIndex]
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MrpackFile {
+    /// Where the file goes, relative to the pack location
+    pub path: String,
+    /// The hashes of the file
+    pub hashes: Hashes,
+    /// Whether the file is needed on the client and server
+    #[serde(default)]
+    pub env: Option<Env>,
+    /// URLs the file can be downloaded from, in the order they should be tried
+    pub downloads: Vec<String>,
+    /// The size of the file in bytes
+    pub file_size: u64,
+}
+
+/// The hashes of a [MrpackFile]
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Hashes {
+    #[serde(default)]
+    pub sha1: Option<String>,
+    #[serde(default)]
+    pub sha512: Option<String>,
+}
+
+impl Hashes {
+    /// The hashes the index gives as [FileHash]es, sha512 first as it is preferred
+    pub fn to_file_hashes(&self) -> Vec<FileHash> {
+        let sha512 = self
+            .sha512
+            .as_ref()
+            .map(|hash| FileHash::Sha512(hash.to_lowercase()));
+        let sha1 = self
+            .sha1
+            .as_ref()
+            .map(|hash| FileHash::Sha1(hash.to_lowercase()));
+        sha512.into_iter().chain(sha1).collect()
+    }
+}
+
+/// Where a [MrpackFile] is needed
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct Env {
+    pub client: EnvSupport,
+    pub server: EnvSupport,
+}
+
+/// If a [MrpackFile] is needed on one side
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvSupport {
+    Required,
+    Optional,
+    Unsupported,
+}
+
+/// The index with `files` left unparsed, so each entry can be checked on its own
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawIndex {
+    format_version: u32,
+    game: String,
+    version_id: String,
+    name: String,
+    #[serde(default)]
+    summary: Option<String>,
+    files: Vec<Value>,
+    dependencies: HashMap<String, String>,
+}
+
+impl MrpackIndex {
+    /// Parses and checks a modrinth.index.json file
+    ///
+    /// Every invalid entry in `files` is reported in the [UpdateError::Index] rather than skipped
+    pub fn parse(json: &str) -> Result<Self> {
+        let raw: RawIndex =
+            serde_json::from_str(json).map_err(|e| UpdateError::Index(e.to_string()))?;
+
+        if raw.format_version != FORMAT_VERSION {
+            return Err(UpdateError::Index(format!(
+                "formatVersion {} is not supported",
+                raw.format_version
+            )));
+        }
+        if raw.game != "minecraft" {
+            return Err(UpdateError::Index(format!(
+                "the pack is for {}, not minecraft",
+                raw.game
+            )));
+        }
+
+        let mut files = Vec::with_capacity(raw.files.len());
+        let mut problems = Vec::new();
+        for (i, entry) in raw.files.into_iter().enumerate() {
+            let path = entry
+                .get("path")
+                .and_then(|v| v.as_str())
+                .unwrap_or("?")
+                .to_string();
+            match serde_json::from_value::<MrpackFile>(entry)
+                .map_err(|e| e.to_string())
+                .and_then(|file| file.validate().map(|_| file))
+            {
+                Ok(mut file) => {
+                    // a path that leaves the pack fails the whole update, not just the entry
+                    let relative = safe_relative_path(&format!("files[{}] ({})", i, path), &path)?;
+                    file.path = relative
+                        .iter()
+                        .map(|part| part.to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    files.push(file);
+                }
+                Err(e) => problems.push(format!("files[{}] ({}): {}", i, path, e)),
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(UpdateError::Index(problems.join("; ")));
+        }
+
+        Ok(Self {
+            format_version: raw.format_version,
+            game: raw.game,
+            version_id: raw.version_id,
+            name: raw.name,
+            summary: raw.summary,
+            files,
+            dependencies: raw.dependencies,
+        })
+    }
+
+    /// The files that should be installed on `side`, given the user's `choices` for optional files
+    pub fn wanted_files<'a>(
+        &'a self,
+        side: Side,
+        choices: &'a BTreeMap<String, bool>,
+    ) -> impl Iterator<Item = &'a MrpackFile> {
+        self.files
+            .iter()
+            .filter(move |file| file.is_wanted(side, choices))
+    }
+
+    /// The files that should not be installed on `side`, they are removed if the pack has them
+    pub fn unwanted_files<'a>(
+        &'a self,
+        side: Side,
+        choices: &'a BTreeMap<String, bool>,
+    ) -> impl Iterator<Item = &'a MrpackFile> {
+        self.files
+            .iter()
+            .filter(move |file| !file.is_wanted(side, choices))
+    }
+
+    /// The files the user can choose to install or not on `side`
+    pub fn optional_files(&self, side: Side) -> impl Iterator<Item = &MrpackFile> {
+        self.files
+            .iter()
+            .filter(move |file| file.support(side) == EnvSupport::Optional)
+    }
+
+    /// The files that may need to be downloaded on `side`, named by their path inside the pack
+    pub fn file_infos(&self, side: Side, choices: &BTreeMap<String, bool>) -> Vec<FileInfo> {
+        self.wanted_files(side, choices)
+            .map(|file| {
+                FileInfo::new(
+                    file.path.clone(),
+                    file.downloads.clone(),
+                    file.hashes.to_file_hashes(),
+                    Some(file.file_size),
+                )
+            })
+            .collect()
+    }
+}
+
+impl MrpackFile {
+    /// If the file is needed, optional or unsupported on `side`, files without an `env` are needed on both
+    pub fn support(&self, side: Side) -> EnvSupport {
+        match (self.env, side) {
+            (None, _) => EnvSupport::Required,
+            (Some(env), Side::Client) => env.client,
+            (Some(env), Side::Server) => env.server,
+        }
+    }
+
+    /// If the file should be installed on `side`, optional files follow the user's `choices`
+    fn is_wanted(&self, side: Side, choices: &BTreeMap<String, bool>) -> bool {
+        match self.support(side) {
+            EnvSupport::Required => true,
+            EnvSupport::Optional => choices.get(&self.path).copied().unwrap_or(true),
+            EnvSupport::Unsupported => false,
+        }
+    }
+
+    /// Checks the parts of the entry that serde can not
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.path.is_empty() {
+            return Err("path is empty".to_string());
+        }
+        if self.downloads.is_empty() {
+            return Err("no download URLs".to_string());
+        }
+        if self.hashes.sha512.is_none() && self.hashes.sha1.is_none() {
+            return Err("no sha512 or sha1 hash".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(files: &[&str]) -> String {
+        format!(
+            r#"{{ "formatVersion": 1, "game": "minecraft", "versionId": "1.0.0", "name": "OG3", "files": [{}], "dependencies": {{}} }}"#,
+            files.join(",")
+        )
+    }
+
+    fn index_error(json: &str) -> String {
+        match MrpackIndex::parse(json) {
+            Err(UpdateError::Index(message)) => message,
+            Err(e) => panic!("expected an index error, got {}", e),
+            Ok(_) => panic!("the index was accepted"),
+        }
+    }
+
+    #[test]
+    fn every_bad_entry_is_reported() {
+        let message = index_error(&index(&[
+            r#"{ "path": "mods/good.jar", "hashes": { "sha1": "aa" }, "downloads": ["https://cdn.modrinth.com/a"], "fileSize": 1 }"#,
+            r#"{ "path": "mods/no-hash.jar", "hashes": {}, "downloads": ["https://cdn.modrinth.com/b"], "fileSize": 1 }"#,
+            r#"{ "path": "mods/no-downloads.jar", "hashes": { "sha1": "aa" }, "downloads": [], "fileSize": 1 }"#,
+            r#"{ "path": "mods/no-size.jar", "hashes": { "sha1": "aa" }, "downloads": ["https://cdn.modrinth.com/c"] }"#,
+        ]));
+
+        assert!(!message.contains("good.jar"), "{}", message);
+        assert!(
+            message.contains("files[1] (mods/no-hash.jar): no sha512 or sha1 hash"),
+            "{}",
+            message
+        );
+        assert!(
+            message.contains("files[2] (mods/no-downloads.jar): no download URLs"),
+            "{}",
+            message
+        );
+        assert!(
+            message.contains("files[3] (mods/no-size.jar)"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn wrong_format_version_and_game_are_refused() {
+        let json = index(&[]);
+        let message = index_error(&json.replace(r#""formatVersion": 1"#, r#""formatVersion": 2"#));
+        assert!(message.contains("formatVersion 2"), "{}", message);
+
+        let message = index_error(&json.replace(r#""game": "minecraft""#, r#""game": "terraria""#));
+        assert!(message.contains("terraria"), "{}", message);
+    }
+
+    #[test]
+    fn sha1_only_entries_are_accepted() {
+        let index = MrpackIndex::parse(&index(&[
+            r#"{ "path": "mods\\a.jar", "hashes": { "sha1": "AB" }, "downloads": ["https://cdn.modrinth.com/a"], "fileSize": 1 }"#,
+        ]))
+        .unwrap();
+
+        assert_eq!(index.files[0].path, "mods/a.jar");
+        assert_eq!(
+            index.files[0].hashes.to_file_hashes(),
+            vec![FileHash::Sha1("ab".to_string())]
+        );
+    }
+
+    #[test]
+    fn sha512_comes_first() {
+        let hashes = Hashes {
+            sha1: Some("AA".to_string()),
+            sha512: Some("BB".to_string()),
+        };
+        assert_eq!(
+            hashes.to_file_hashes(),
+            vec![
+                FileHash::Sha512("bb".to_string()),
+                FileHash::Sha1("aa".to_string())
+            ]
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use crate::error::{PathContext, Result, UpdateError};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The file older versions of the Modrinth App keep the instance's game and loader versions in
+const PROFILE_FILE: &str = "profile.json";
+
+/// The `dependencies` keys of the index that the updater checks, with the loader name the Modrinth App uses
+const COMPONENTS: [(&str, &str); 5] = [
+    ("minecraft", "vanilla"),
+    ("fabric-loader", "fabric"),
+    ("forge", "forge"),
+    ("neoforge", "neoforge"),
+    ("quilt-loader", "quilt"),
+];
+
+/// A Minecraft or mod loader version the pack needs that the instance does not have
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionMismatch {
+    /// The `dependencies` key of the index, e.g. "fabric-loader"
+    pub component: String,
+    /// The version the pack needs
+    pub required: String,
+    /// The version the instance has, `None` if it has a different loader
+    pub installed: Option<String>,
+}
+
+impl VersionMismatch {
+    /// A line describing the mismatch for the user
+    pub fn describe(&self) -> String {
+        match &self.installed {
+            Some(installed) => format!(
+                "{} {} is needed but the instance has {}",
+                display_name(&self.component),
+                self.required,
+                installed
+            ),
+            None => format!(
+                "{} {} is needed but the instance does not use it",
+                display_name(&self.component),
+                self.required
+            ),
+        }
+    }
+}
+
+/// The name of a `dependencies` key for the user
+fn display_name(component: &str) -> &str {
+    match component {
+        "minecraft" => "Minecraft",
+        "fabric-loader" => "Fabric Loader",
+        "forge" => "Forge",
+        "neoforge" => "NeoForge",
+        "quilt-loader" => "Quilt Loader",
+        other => other,
+    }
+}
+
+/// The Minecraft and loader versions of the instance the pack is installed in
+pub struct Instance {
+    /// The launcher's profile file, `None` if the launcher keeps it somewhere the updater can not change
+    profile: Option<PathBuf>,
+    /// The versions of the instance by `dependencies` key
+    versions: BTreeMap<String, String>,
+}
+
+impl Instance {
+    /// Reads the versions from the launcher's profile in the pack folder
+    ///
+    /// Without one, the `dependencies` the updater installed last time are used
+    pub fn load(pack_location: &Path, last_installed: &BTreeMap<String, String>) -> Self {
+        let path = pack_location.join(PROFILE_FILE);
+        match read_profile(&path) {
+            Some(versions) => Self {
+                profile: Some(path),
+                versions,
+            },
+            None => Self {
+                profile: None,
+                versions: last_installed.clone(),
+            },
+        }
+    }
+
+    /// If [Instance::update] can change the launcher's versions
+    pub fn can_update(&self) -> bool {
+        self.profile.is_some()
+    }
+
+    /// The Minecraft version of the instance, if it is known
+    pub fn game_version(&self) -> Option<&str> {
+        self.versions.get("minecraft").map(|v| v.as_str())
+    }
+
+    /// The loader of the instance by the name the Modrinth App uses, e.g. "fabric"
+    pub fn loader(&self) -> Option<&str> {
+        COMPONENTS
+            .iter()
+            .skip(1)
+            .find(|(component, _)| self.versions.contains_key(*component))
+            .map(|(_, loader)| *loader)
+    }
+
+    /// The versions in the index `dependencies` that the instance does not have
+    ///
+    /// Nothing is reported when the instance versions are not known
+    pub fn mismatches(&self, dependencies: &HashMap<String, String>) -> Vec<VersionMismatch> {
+        if self.versions.is_empty() {
+            return Vec::new();
+        }
+
+        COMPONENTS
+            .iter()
+            .filter_map(|(component, _)| {
+                let required = dependencies.get(*component)?;
+                let installed = self.versions.get(*component);
+                (installed != Some(required)).then(|| VersionMismatch {
+                    component: component.to_string(),
+                    required: required.clone(),
+                    installed: installed.cloned(),
+                })
+            })
+            .collect()
+    }
+
+    /// Switches the launcher's profile to the versions in the index `dependencies`
+    pub fn update(&self, dependencies: &HashMap<String, String>) -> Result<()> {
+        let Some(path) = &self.profile else {
+            return Err(UpdateError::Config(
+                "the launcher's instance settings can not be changed by the updater".to_string(),
+            ));
+        };
+
+        let contents = fs::read_to_string(path).with_path(path)?;
+        let mut profile: Value =
+            serde_json::from_str(&contents).map_err(|e| UpdateError::Config(e.to_string()))?;
+        let metadata = profile
+            .get_mut("metadata")
+            .and_then(|m| m.as_object_mut())
+            .ok_or_else(|| UpdateError::Config(format!("{} has no metadata", path.display())))?;
+
+        if let Some(version) = dependencies.get("minecraft") {
+            metadata.insert("game_version".to_string(), Value::from(version.as_str()));
+        }
+        for (component, loader) in COMPONENTS.iter().skip(1) {
+            if let Some(version) = dependencies.get(*component) {
+                println!("Switching the instance to {} {}", loader, version);
+                metadata.insert("loader".to_string(), Value::from(*loader));
+
+                // keep the other loader version fields the launcher saved
+                let mut loader_version = metadata
+                    .get("loader_version")
+                    .and_then(|v| v.as_object())
+                    .cloned()
+                    .unwrap_or_default();
+                loader_version.insert("id".to_string(), Value::from(version.as_str()));
+                metadata.insert("loader_version".to_string(), Value::from(loader_version));
+            }
+        }
+
+        let json = serde_json::to_string_pretty(&profile)
+            .map_err(|e| UpdateError::Config(e.to_string()))?;
+        fs::write(path, json).with_path(path)
+    }
+}
+
+/// Reads the versions from a profile file, `None` if it does not exist or is not in a known format
+fn read_profile(path: &Path) -> Option<BTreeMap<String, String>> {
+    let profile: Value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    let metadata = profile.get("metadata")?;
+
+    let mut versions = BTreeMap::new();
+    versions.insert(
+        "minecraft".to_string(),
+        metadata.get("game_version")?.as_str()?.to_string(),
+    );
+
+    let loader = metadata.get("loader").and_then(|v| v.as_str());
+    let loader_version = metadata
+        .get("loader_version")
+        .and_then(|v| v.get("id"))
+        .and_then(|v| v.as_str());
+    if let (Some(loader), Some(version)) = (loader, loader_version) {
+        if let Some((component, _)) = COMPONENTS.iter().skip(1).find(|(_, l)| *l == loader) {
+            versions.insert(component.to_string(), version.to_string());
+        }
+    }
+
+    Some(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TestDir;
+
+    /// A profile as older versions of the Modrinth App write it, trimmed to what matters here
+    const PROFILE: &str = r#"{
+        "path": "OG3 1.0.0",
+        "metadata": {
+            "name": "OG3",
+            "game_version": "1.20.1",
+            "loader": "fabric",
+            "loader_version": { "id": "0.15.0", "stable": true }
+        }
+    }"#;
+
+    /// A pack folder in the temp dir for one test, with `profile` as its profile file
+    fn test_pack(name: &str, profile: Option<&str>) -> TestDir {
+        let pack = TestDir::new(&format!("instance-{}", name));
+        if let Some(profile) = profile {
+            fs::write(pack.join(PROFILE_FILE), profile).unwrap();
+        }
+        pack
+    }
+
+    fn dependencies(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(component, version)| (component.to_string(), version.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn profile_versions_are_read() {
+        let pack = test_pack("read", Some(PROFILE));
+
+        let versions = read_profile(&pack.join(PROFILE_FILE)).unwrap();
+        assert_eq!(versions["minecraft"], "1.20.1");
+        assert_eq!(versions["fabric-loader"], "0.15.0");
+        assert_eq!(versions.len(), 2);
+
+        fs::write(pack.join(PROFILE_FILE), r#"{ "metadata": {} }"#).unwrap();
+        assert_eq!(read_profile(&pack.join(PROFILE_FILE)), None);
+    }
+
+    #[test]
+    fn mismatches_list_the_versions_the_instance_does_not_have() {
+        let pack = test_pack("mismatches", Some(PROFILE));
+        let instance = Instance::load(&pack, &BTreeMap::new());
+        assert_eq!(instance.loader(), Some("fabric"));
+
+        let same = dependencies(&[("minecraft", "1.20.1"), ("fabric-loader", "0.15.0")]);
+        assert!(instance.mismatches(&same).is_empty());
+
+        let newer = dependencies(&[("minecraft", "1.20.1"), ("fabric-loader", "0.16.0")]);
+        assert_eq!(
+            instance.mismatches(&newer),
+            [VersionMismatch {
+                component: "fabric-loader".to_string(),
+                required: "0.16.0".to_string(),
+                installed: Some("0.15.0".to_string()),
+            }]
+        );
+
+        let other_loader = dependencies(&[("minecraft", "1.20.1"), ("quilt-loader", "0.20.0")]);
+        assert_eq!(instance.mismatches(&other_loader)[0].installed, None);
+    }
+
+    #[test]
+    fn without_a_profile_the_last_installed_versions_are_used() {
+        let pack = test_pack("no-profile", None);
+        let last = BTreeMap::from([("minecraft".to_string(), "1.20.1".to_string())]);
+
+        let instance = Instance::load(&pack, &last);
+        assert!(!instance.can_update());
+        assert_eq!(instance.game_version(), Some("1.20.1"));
+        assert!(instance
+            .update(&dependencies(&[("minecraft", "1.21")]))
+            .is_err());
+
+        // nothing is known about an instance the updater never installed
+        let unknown = Instance::load(&pack, &BTreeMap::new());
+        assert!(unknown
+            .mismatches(&dependencies(&[("minecraft", "1.21")]))
+            .is_empty());
+    }
+
+    #[test]
+    fn update_switches_the_profile_and_keeps_other_fields() {
+        let pack = test_pack("update", Some(PROFILE));
+        let instance = Instance::load(&pack, &BTreeMap::new());
+        assert!(instance.can_update());
+
+        let newer = dependencies(&[("minecraft", "1.20.4"), ("fabric-loader", "0.16.0")]);
+        instance.update(&newer).unwrap();
+
+        assert!(Instance::load(&pack, &BTreeMap::new())
+            .mismatches(&newer)
+            .is_empty());
+        let profile: Value =
+            serde_json::from_str(&fs::read_to_string(pack.join(PROFILE_FILE)).unwrap()).unwrap();
+        assert_eq!(profile["path"], "OG3 1.0.0");
+        assert_eq!(profile["metadata"]["name"], "OG3");
+        assert_eq!(profile["metadata"]["loader_version"]["stable"], true);
+    }
+}
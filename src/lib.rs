@@ -0,0 +1,78 @@
+//! The update logic for the OG3 Pack Updater
+//!
+//! An update is run in explicit stages through the [Updater]:
+//! [Updater::check] for a new release, [Updater::plan] what needs to change and [Updater::apply] the plan.
+//! Progress is reported through the [Progress] trait so the UI is just one consumer of this library.
+
+pub mod config;
+pub mod disk;
+pub mod download;
+pub mod error;
+pub mod github;
+pub mod hash;
+pub mod index;
+pub mod instance;
+pub mod manifest;
+pub mod modrinth;
+pub mod paths;
+pub mod progress;
+pub mod release;
+pub mod snapshot;
+pub mod source;
+pub mod staging;
+pub mod updater;
+
+#[cfg(test)]
+#[path = "../tests/common/temp_dir.rs"]
+mod temp_dir;
+
+pub use config::Config;
+pub use error::UpdateError;
+pub use hash::FileHash;
+pub use progress::{Phase, Progress, ProgressInfo};
+pub use updater::{OptionalFile, UpdateCheck, UpdatePlan, Updater};
+
+/// The User-Agent sent with every request
+pub const USER_AGENT: &str = "interstellarfrog/OG3-pack-updater";
+
+/// [Struct] for holding information about files
+#[derive(Clone, Debug)]
+pub struct FileInfo {
+    /// The name of the file
+    pub name: String,
+    /// The download URLs of the file, in the order they should be tried
+    pub urls: Vec<String>,
+    /// The hashes of the file, strongest first, empty if the file is not checked
+    pub hashes: Vec<FileHash>,
+    /// The size of the file in bytes
+    pub size: Option<u64>,
+}
+
+impl FileInfo {
+    pub fn new(name: String, urls: Vec<String>, hashes: Vec<FileHash>, size: Option<u64>) -> Self {
+        Self {
+            name,
+            urls,
+            hashes,
+            size,
+        }
+    }
+}
+
+/// Formats a number of bytes for showing to the user e.g. "1.5 MB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, UNITS[unit])
+}
@@ -0,0 +1,92 @@
+use crate::error::Result;
+use crate::index::Hashes;
+use crate::release::{Asset, Release};
+use crate::source::ReleaseSource;
+use crate::USER_AGENT;
+use serde::Deserialize;
+
+/// A JSON file describing the latest version of the pack, so it can be hosted anywhere e.g. on og3.infy.uk
+///
+/// ```json
+/// {
+///     "version": "1.2.0",
+///     "assets": [
+///         {
+///             "name": "OG3.zip",
+///             "url": "https://og3.infy.uk/files/OG3-1.2.0.zip",
+///             "size": 123456,
+///             "hashes": { "sha512": "...", "sha1": "..." }
+///         }
+///     ]
+/// }
+/// ```
+///
+/// `version` is the `versionId` of the pack's index, each asset is a pack zip or a .mrpack.
+/// A manifest only lists one version, so it is used on every [Channel](crate::config::Channel)
+#[derive(Deserialize, Debug)]
+pub struct Manifest {
+    pub version: String,
+    pub assets: Vec<ManifestAsset>,
+}
+
+/// A file in a [Manifest]
+#[derive(Deserialize, Debug)]
+pub struct ManifestAsset {
+    pub name: String,
+    pub url: String,
+    /// The size of the file in bytes
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// The hashes the download has to match
+    #[serde(default)]
+    pub hashes: Hashes,
+}
+
+impl From<Manifest> for Release {
+    fn from(manifest: Manifest) -> Self {
+        Release {
+            tag_name: manifest.version,
+            prerelease: false,
+            source: String::new(),
+            assets: manifest
+                .assets
+                .into_iter()
+                .map(|asset| Asset {
+                    hashes: asset.hashes.to_file_hashes(),
+                    name: asset.name,
+                    browser_download_url: asset.url,
+                    size: asset.size,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Gets the latest version of the pack from a [Manifest] at a URL
+pub struct ManifestSource {
+    url: String,
+}
+
+impl ManifestSource {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+        }
+    }
+}
+
+impl ReleaseSource for ManifestSource {
+    fn name(&self) -> String {
+        format!("manifest {}", self.url)
+    }
+
+    fn latest(&self, client: &reqwest::blocking::Client) -> Result<Release> {
+        let manifest: Manifest = client
+            .get(&self.url)
+            .header("User-Agent", USER_AGENT)
+            .send()?
+            .error_for_status()?
+            .json()?;
+        Ok(manifest.into())
+    }
+}
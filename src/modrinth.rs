@@ -0,0 +1,168 @@
+use crate::config::Channel;
+use crate::error::{Result, UpdateError};
+use crate::index::Hashes;
+use crate::release::{Asset, Release};
+use crate::source::ReleaseSource;
+use crate::USER_AGENT;
+use serde::Deserialize;
+
+/// The Modrinth v2 API
+pub const API_URL: &str = "https://api.modrinth.com/v2";
+
+/// A version of a project from the Modrinth API
+#[derive(Deserialize, Debug)]
+struct Version {
+    version_number: String,
+    /// When the version was published, an RFC 3339 timestamp
+    date_published: String,
+    /// "release", "beta" or "alpha"
+    #[serde(default)]
+    version_type: Option<String>,
+    #[serde(default)]
+    game_versions: Vec<String>,
+    #[serde(default)]
+    loaders: Vec<String>,
+    /// The loaders a modpack version is for, modpack versions only list "mrpack" in `loaders`
+    #[serde(default)]
+    mrpack_loaders: Vec<String>,
+    files: Vec<VersionFile>,
+}
+
+impl Version {
+    /// If the version is a beta or alpha, versions without a type count as releases
+    fn is_prerelease(&self) -> bool {
+        self.version_type
+            .as_deref()
+            .is_some_and(|version_type| version_type != "release")
+    }
+}
+
+/// A file of a [Version]
+#[derive(Deserialize, Debug)]
+struct VersionFile {
+    url: String,
+    filename: String,
+    primary: bool,
+    size: u64,
+    hashes: Hashes,
+}
+
+/// Gets versions of the pack straight from its Modrinth project, the .mrpack is downloaded without a zip around it
+///
+/// Only versions for the instance's Minecraft version and loader are used, when they are known
+pub struct ModrinthSource {
+    project: String,
+    api_url: String,
+    game_version: Option<String>,
+    loader: Option<String>,
+    channel: Channel,
+}
+
+impl ModrinthSource {
+    /// Creates a source for the project with the slug or id `project`, using the API at `api_url`
+    pub fn new(project: &str, api_url: &str) -> Self {
+        Self {
+            project: project.to_string(),
+            api_url: api_url.trim_end_matches('/').to_string(),
+            game_version: None,
+            loader: None,
+            channel: Channel::default(),
+        }
+    }
+
+    /// Finds the newest version on `channel`, beta versions are only used on [Channel::Beta]
+    pub fn with_channel(mut self, channel: Channel) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// Only uses versions for this Minecraft version and loader, e.g. "1.20.1" and "fabric"
+    pub fn with_instance(mut self, game_version: Option<&str>, loader: Option<&str>) -> Self {
+        self.game_version = game_version.map(str::to_string);
+        self.loader = loader.map(str::to_string);
+        self
+    }
+
+    /// If the version can be installed in the instance
+    fn matches(&self, version: &Version) -> bool {
+        let game_version = self
+            .game_version
+            .as_ref()
+            .is_none_or(|wanted| version.game_versions.contains(wanted));
+        let loader = self.loader.as_ref().is_none_or(|wanted| {
+            version.loaders.contains(wanted) || version.mrpack_loaders.contains(wanted)
+        });
+        let channel = match self.channel {
+            Channel::Stable => !version.is_prerelease(),
+            // alpha versions are too unfinished for testers
+            Channel::Beta => version.version_type.as_deref() != Some("alpha"),
+        };
+        game_version && loader && channel
+    }
+}
+
+impl ReleaseSource for ModrinthSource {
+    fn name(&self) -> String {
+        format!("Modrinth project {}", self.project)
+    }
+
+    fn latest(&self, client: &reqwest::blocking::Client) -> Result<Release> {
+        let url = format!("{}/project/{}/version", self.api_url, self.project);
+        let mut request = client.get(url).header("User-Agent", USER_AGENT);
+        // the loader is checked here rather than in the query as modpack versions only list "mrpack" as their loader
+        if let Some(game_version) = &self.game_version {
+            request = request.query(&[("game_versions", format!("[\"{}\"]", game_version))]);
+        }
+        let versions: Vec<Version> = request.send()?.error_for_status()?.json()?;
+
+        let version = versions
+            .into_iter()
+            .filter(|version| self.matches(version))
+            .max_by(|a, b| a.date_published.cmp(&b.date_published))
+            .ok_or_else(|| {
+                let wanted: Vec<&str> = [&self.game_version, &self.loader]
+                    .into_iter()
+                    .flatten()
+                    .map(|s| s.as_str())
+                    .collect();
+                UpdateError::Release(format!(
+                    "{} has no versions for {}",
+                    self.project,
+                    if wanted.is_empty() {
+                        "this pack".to_string()
+                    } else {
+                        wanted.join(" ")
+                    }
+                ))
+            })?;
+
+        let file = version
+            .files
+            .iter()
+            .find(|file| file.primary && file.filename.ends_with(".mrpack"))
+            .or_else(|| {
+                version
+                    .files
+                    .iter()
+                    .find(|file| file.filename.ends_with(".mrpack"))
+            })
+            .ok_or_else(|| {
+                UpdateError::Release(format!(
+                    "{} has no .mrpack to download",
+                    version.version_number
+                ))
+            })?;
+
+        Ok(Release {
+            assets: vec![Asset {
+                name: file.filename.clone(),
+                browser_download_url: file.url.clone(),
+                size: Some(file.size),
+                hashes: file.hashes.to_file_hashes(),
+            }],
+            prerelease: version.is_prerelease(),
+            source: String::new(),
+            tag_name: version.version_number,
+        })
+    }
+}
@@ -0,0 +1,102 @@
+use crate::error::{Result, UpdateError};
+use std::path::PathBuf;
+
+/// Names Windows will not create a file with, with or without an extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Checks a path from the .mrpack stays inside the pack and turns it into a [PathBuf] relative to the pack
+///
+/// Every index `path` and override entry goes through this before it is joined onto the pack location.
+/// `entry` names the entry in the [UpdateError::UnsafePath] if the path is rejected.
+pub fn safe_relative_path(entry: &str, path: &str) -> Result<PathBuf> {
+    let unsafe_path = |reason: &str| UpdateError::UnsafePath {
+        entry: entry.to_string(),
+        reason: reason.to_string(),
+    };
+
+    if path.starts_with('/') || path.starts_with('\\') {
+        return Err(unsafe_path("absolute paths are not allowed"));
+    }
+
+    let mut relative = PathBuf::new();
+    for segment in path.split(['/', '\\']) {
+        // empty and "." segments do not go anywhere
+        if segment.is_empty() || segment == "." {
+            continue;
+        }
+        if segment == ".." {
+            return Err(unsafe_path("'..' is not allowed"));
+        }
+        if segment.contains(':') {
+            return Err(unsafe_path("drive prefixes and ':' are not allowed"));
+        }
+        if segment.chars().any(|c| c.is_control()) {
+            return Err(unsafe_path("control characters are not allowed"));
+        }
+
+        let stem = segment.split('.').next().unwrap_or(segment).trim_end();
+        if RESERVED_NAMES
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(stem))
+        {
+            return Err(unsafe_path("reserved Windows names are not allowed"));
+        }
+
+        relative.push(segment);
+    }
+
+    if relative.as_os_str().is_empty() {
+        return Err(unsafe_path("the path is empty"));
+    }
+
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_rejected(path: &str) -> bool {
+        matches!(
+            safe_relative_path(path, path),
+            Err(UpdateError::UnsafePath { .. })
+        )
+    }
+
+    #[test]
+    fn accepts_nested_paths() {
+        assert_eq!(
+            safe_relative_path("e", "mods/sub/a.jar").unwrap(),
+            PathBuf::from("mods").join("sub").join("a.jar")
+        );
+        assert_eq!(
+            safe_relative_path("e", "./config//x.toml").unwrap(),
+            PathBuf::from("config").join("x.toml")
+        );
+        assert!(safe_relative_path("e", "config/console.json").is_ok());
+    }
+
+    #[test]
+    fn rejects_escaping_paths() {
+        assert!(is_rejected("/etc/passwd"));
+        assert!(is_rejected("\\Windows\\System32"));
+        assert!(is_rejected("../outside.jar"));
+        assert!(is_rejected("mods/../../outside.jar"));
+        assert!(is_rejected("mods\\..\\..\\outside.jar"));
+        assert!(is_rejected("C:/Windows/evil.dll"));
+        assert!(is_rejected("C:evil.dll"));
+        assert!(is_rejected(""));
+        assert!(is_rejected("./"));
+    }
+
+    #[test]
+    fn rejects_reserved_windows_names() {
+        assert!(is_rejected("mods/CON"));
+        assert!(is_rejected("mods/nul.jar"));
+        assert!(is_rejected("Com1.txt"));
+        assert!(is_rejected("lpt9/a.jar"));
+    }
+}
@@ -0,0 +1,239 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often a [Tracker] sends updates while the phase and file stay the same
+const UPDATE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The stages of an update in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    DownloadingPack,
+    HashingMods,
+    ReadingPack,
+    Extracting,
+    DownloadingFiles,
+    Installing,
+    Done,
+}
+
+impl Phase {
+    /// The name shown to the user
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::DownloadingPack => "Downloading pack",
+            Phase::HashingMods => "Checking installed mods",
+            Phase::ReadingPack => "Reading pack",
+            Phase::Extracting => "Extracting overrides",
+            Phase::DownloadingFiles => "Downloading files",
+            Phase::Installing => "Installing",
+            Phase::Done => "Done",
+        }
+    }
+
+    /// The part of the overall progress this phase covers
+    fn range(&self) -> (f32, f32) {
+        match self {
+            Phase::DownloadingPack => (0.0, 0.2),
+            Phase::HashingMods => (0.2, 0.3),
+            Phase::ReadingPack => (0.3, 0.35),
+            Phase::Extracting => (0.35, 0.45),
+            Phase::DownloadingFiles => (0.45, 0.9),
+            Phase::Installing => (0.9, 1.0),
+            Phase::Done => (1.0, 1.0),
+        }
+    }
+
+    /// If the phase counts bytes rather than files, so has a speed and ETA
+    fn counts_bytes(&self) -> bool {
+        matches!(self, Phase::DownloadingPack | Phase::DownloadingFiles)
+    }
+}
+
+/// How far along an update is, sent to [Progress::update]
+#[derive(Debug, Clone)]
+pub struct ProgressInfo {
+    pub phase: Phase,
+    /// The file being worked on, empty if there is none
+    pub current_file: String,
+    /// Bytes or files done in this phase
+    pub done: u64,
+    /// Bytes or files in this phase, 0 if it is not known
+    pub total: u64,
+    /// The overall progress of the update between 0.0 and 1.0
+    pub fraction: f32,
+    /// The transfer speed while downloading
+    pub bytes_per_second: Option<f64>,
+    /// The estimated time left in this phase while downloading
+    pub eta: Option<Duration>,
+}
+
+/// Receives progress updates from the [Updater](crate::Updater)
+pub trait Progress: Send + Sync {
+    /// Called when the phase or file changes, and regularly while data is transferred
+    fn update(&self, info: &ProgressInfo);
+}
+
+/// No progress reporting
+impl Progress for () {
+    fn update(&self, _info: &ProgressInfo) {}
+}
+
+/// The changing part of a [Tracker]
+struct TrackerState {
+    phase: Phase,
+    current_file: String,
+    done: u64,
+    total: u64,
+    started: Instant,
+    last_sent: Instant,
+}
+
+/// Keeps count of the work done in each [Phase] and turns it into [ProgressInfo] for a [Progress]
+///
+/// It can be shared between download threads, updates are limited so the UI is not flooded
+pub struct Tracker<'a> {
+    progress: &'a dyn Progress,
+    state: Mutex<TrackerState>,
+}
+
+impl<'a> Tracker<'a> {
+    pub fn new(progress: &'a dyn Progress) -> Self {
+        let now = Instant::now();
+        Self {
+            progress,
+            state: Mutex::new(TrackerState {
+                phase: Phase::DownloadingPack,
+                current_file: String::new(),
+                done: 0,
+                total: 0,
+                started: now,
+                last_sent: now,
+            }),
+        }
+    }
+
+    /// Starts a new [Phase] with `total` bytes or files of work, 0 if it is not known
+    pub fn phase(&self, phase: Phase, total: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.phase = phase;
+        state.current_file.clear();
+        state.done = 0;
+        state.total = total;
+        state.started = Instant::now();
+        self.send(&mut state);
+    }
+
+    /// Sets the total work in this phase once it is known
+    pub fn set_total(&self, total: u64) {
+        self.state.lock().unwrap().total = total;
+    }
+
+    /// Sets the file being worked on
+    pub fn file(&self, name: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.current_file = name.to_string();
+        if state.last_sent.elapsed() >= UPDATE_INTERVAL {
+            self.send(&mut state);
+        }
+    }
+
+    /// Adds `amount` bytes or files to the work done in this phase
+    pub fn advance(&self, amount: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.done += amount;
+        // a phase with an unknown total is never finished early
+        let finished = state.total > 0 && state.done >= state.total;
+        if state.last_sent.elapsed() >= UPDATE_INTERVAL || finished {
+            self.send(&mut state);
+        }
+    }
+
+    /// Takes back `amount` bytes or files, for a download attempt that failed
+    pub fn rewind(&self, amount: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.done = state.done.saturating_sub(amount);
+    }
+
+    /// Sends the current [ProgressInfo]
+    fn send(&self, state: &mut TrackerState) {
+        state.last_sent = Instant::now();
+
+        let (start, end) = state.phase.range();
+        let phase_fraction = if state.total > 0 {
+            (state.done as f32 / state.total as f32).min(1.0)
+        } else {
+            0.0
+        };
+
+        let elapsed = state.started.elapsed().as_secs_f64();
+        let bytes_per_second = (state.phase.counts_bytes() && elapsed > 0.0 && state.done > 0)
+            .then(|| state.done as f64 / elapsed);
+        let eta = bytes_per_second.filter(|_| state.total > 0).map(|speed| {
+            Duration::from_secs_f64(state.total.saturating_sub(state.done) as f64 / speed)
+        });
+
+        self.progress.update(&ProgressInfo {
+            phase: state.phase,
+            current_file: state.current_file.clone(),
+            done: state.done,
+            total: state.total,
+            fraction: start + (end - start) * phase_fraction,
+            bytes_per_second,
+            eta,
+        });
+    }
+}
+
+/// Formats a [Duration] for showing to the user e.g. "1m 20s"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Counts the updates it gets
+    #[derive(Default)]
+    struct Counter(AtomicUsize);
+
+    impl Progress for Counter {
+        fn update(&self, _info: &ProgressInfo) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn unknown_total_is_not_sent_on_every_advance() {
+        let counter = Counter::default();
+        let tracker = Tracker::new(&counter);
+
+        tracker.phase(Phase::DownloadingPack, 0);
+        for _ in 0..100 {
+            tracker.advance(8192);
+        }
+
+        // only the start of the phase, the interval has not passed yet
+        assert_eq!(counter.0.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn finishing_a_phase_is_sent_straight_away() {
+        let counter = Counter::default();
+        let tracker = Tracker::new(&counter);
+
+        tracker.phase(Phase::HashingMods, 2);
+        tracker.advance(1);
+        tracker.advance(1);
+
+        assert_eq!(counter.0.load(Ordering::Relaxed), 2);
+    }
+}
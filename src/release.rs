@@ -0,0 +1,43 @@
+use crate::error::{Result, UpdateError};
+use crate::FileHash;
+use serde::Deserialize;
+
+/// [Struct] for holding a release of the pack from a [ReleaseSource](crate::source::ReleaseSource)
+///
+/// This matches the GitHub API, other sources convert their releases into it
+#[derive(Debug, Deserialize, Clone)]
+pub struct Release {
+    pub tag_name: String,
+    pub assets: Vec<Asset>,
+    /// If the release is a pre-release, only installed on the beta [Channel](crate::config::Channel)
+    #[serde(default)]
+    pub prerelease: bool,
+    /// The name of the [ReleaseSource](crate::source::ReleaseSource) the release is from, set by [Updater::check](crate::Updater::check)
+    #[serde(skip)]
+    pub source: String,
+}
+
+/// [Struct] for holding a file attached to a [Release]
+#[derive(Debug, Deserialize, Clone)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+    /// The size of the file in bytes
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// The hashes the download has to match, sources that do not give any leave this empty
+    #[serde(default, skip)]
+    pub hashes: Vec<FileHash>,
+}
+
+impl Release {
+    /// Finds the pack zip or .mrpack in the release assets
+    pub fn pack_asset(&self) -> Result<&Asset> {
+        self.assets
+            .iter()
+            .find(|a| a.name.ends_with(".zip") || a.name.ends_with(".mrpack"))
+            .ok_or_else(|| {
+                UpdateError::Release(format!("{} has no pack zip to download", self.tag_name))
+            })
+    }
+}
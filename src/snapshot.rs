@@ -0,0 +1,299 @@
+use crate::error::{PathContext, Result, UpdateError};
+use crate::staging::{link_or_copy, Staging};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The folders of the pack that are always saved in a snapshot
+pub const SNAPSHOT_ENTRIES: [&str; 4] = ["mods", "resourcepacks", "shaderpacks", "config"];
+
+/// Added to the pack folder name to get the folder snapshots are kept in
+const SNAPSHOTS_SUFFIX: &str = ".og3-snapshots";
+/// The file in each snapshot that holds its [SnapshotInfo]
+const INFO_FILE: &str = "snapshot.json";
+
+/// What is saved about a snapshot next to its files
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct SnapshotInfo {
+    /// The pack version the snapshot was taken of
+    version: String,
+    /// When the snapshot was taken, in seconds since the unix epoch
+    created: u64,
+    /// The top level files and folders of the pack that were saved, ones missing from the snapshot did not exist
+    #[serde(default = "default_entries")]
+    entries: Vec<String>,
+    /// The Minecraft and loader versions the pack version needed
+    #[serde(default)]
+    dependencies: BTreeMap<String, String>,
+    /// The paths of the index files the pack version installed
+    #[serde(default)]
+    files: Vec<String>,
+}
+
+/// Snapshots from before the entries were saved only have the [SNAPSHOT_ENTRIES]
+fn default_entries() -> Vec<String> {
+    SNAPSHOT_ENTRIES.iter().map(|s| s.to_string()).collect()
+}
+
+/// A copy of the managed folders of the pack taken before an update
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// The folder holding the snapshot
+    pub path: PathBuf,
+    /// The pack version the snapshot was taken of
+    pub version: String,
+    /// When the snapshot was taken, in seconds since the unix epoch
+    pub created: u64,
+    /// The top level files and folders of the pack that are put back by [Snapshot::restore]
+    pub entries: Vec<String>,
+    /// The Minecraft and loader versions the pack version needed
+    pub dependencies: BTreeMap<String, String>,
+    /// The paths of the index files the pack version installed
+    pub files: Vec<String>,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of the [SNAPSHOT_ENTRIES] and `extra` entries of the pack, then deletes the oldest snapshots so only `keep` are left
+    pub fn take(
+        pack_location: &Path,
+        version: &str,
+        keep: usize,
+        extra: &[String],
+        dependencies: &BTreeMap<String, String>,
+        files: &[String],
+    ) -> Result<Self> {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = snapshots_dir(pack_location).join(format!("{}-{}", created, version));
+        println!("Taking snapshot of {} at {:?}", version, path);
+
+        fs::create_dir_all(&path).with_path(&path)?;
+
+        let mut entries = default_entries();
+        for name in extra {
+            if !entries.contains(name) {
+                entries.push(name.clone());
+            }
+        }
+
+        let result = entries.iter().try_for_each(|name| {
+            let current = pack_location.join(name);
+            if current.exists() {
+                copy_entry(&current, &path.join(name))
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = result {
+            let _ = fs::remove_dir_all(&path);
+            return Err(e);
+        }
+
+        let info = SnapshotInfo {
+            version: version.to_string(),
+            created,
+            entries,
+            dependencies: dependencies.clone(),
+            files: files.to_vec(),
+        };
+        let info_path = path.join(INFO_FILE);
+        let json =
+            serde_json::to_string_pretty(&info).map_err(|e| UpdateError::Config(e.to_string()))?;
+        fs::write(&info_path, json).with_path(&info_path)?;
+
+        // only keep the newest snapshots
+        for old in Self::list(pack_location).into_iter().skip(keep) {
+            println!("Deleting old snapshot {:?}", old.path);
+            let _ = fs::remove_dir_all(&old.path);
+        }
+
+        Ok(Self {
+            path,
+            version: info.version,
+            created,
+            entries: info.entries,
+            dependencies: info.dependencies,
+            files: info.files,
+        })
+    }
+
+    /// All snapshots of the pack, newest first
+    pub fn list(pack_location: &Path) -> Vec<Self> {
+        let Ok(entries) = fs::read_dir(snapshots_dir(pack_location)) else {
+            return Vec::new();
+        };
+
+        let mut snapshots: Vec<Self> = entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let info: SnapshotInfo =
+                    serde_json::from_str(&fs::read_to_string(path.join(INFO_FILE)).ok()?).ok()?;
+                Some(Self {
+                    path,
+                    version: info.version,
+                    created: info.created,
+                    entries: info.entries,
+                    dependencies: info.dependencies,
+                    files: info.files,
+                })
+            })
+            .collect();
+
+        snapshots.sort_by_key(|snapshot| std::cmp::Reverse(snapshot.created));
+        snapshots
+    }
+
+    /// The newest snapshot of the pack
+    pub fn latest(pack_location: &Path) -> Option<Self> {
+        Self::list(pack_location).into_iter().next()
+    }
+
+    /// Puts the snapshot back into the pack and deletes it
+    ///
+    /// The restore goes through [Staging] so the pack is left as it was if anything fails
+    pub fn restore(self, pack_location: &Path) -> Result<()> {
+        println!(
+            "Restoring snapshot of {} from {:?}",
+            self.version, self.path
+        );
+
+        // every entry starts out empty, so entries that were not in the snapshot are removed
+        let fresh: Vec<&str> = self.entries.iter().map(|s| s.as_str()).collect();
+        let staging = Staging::create(pack_location, self.entries.clone(), &fresh)?;
+
+        let staged = staging.path();
+        let result = self.entries.iter().try_for_each(|name| {
+            let saved = self.path.join(name);
+            if saved.exists() {
+                link_or_copy(&saved, &staged.join(name))
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = result {
+            staging.discard();
+            return Err(e);
+        }
+
+        staging.swap()?;
+
+        fs::remove_dir_all(&self.path).with_path(&self.path)
+    }
+}
+
+/// The folder snapshots of a pack are kept in, next to the pack so files can be hard linked
+fn snapshots_dir(pack_location: &Path) -> PathBuf {
+    let mut name = pack_location.file_name().unwrap_or_default().to_os_string();
+    name.push(SNAPSHOTS_SUFFIX);
+    pack_location.with_file_name(name)
+}
+
+/// Copies a file or folder into a snapshot
+///
+/// Mods and packs are hard linked as the updater never changes them in place,
+/// anything else (like configs and shader settings) can be changed by the game so is copied
+fn copy_entry(src: &Path, dst: &Path) -> Result<()> {
+    if src.is_dir() {
+        fs::create_dir_all(dst).with_path(dst)?;
+        for entry in fs::read_dir(src).with_path(src)? {
+            let entry = entry.with_path(src)?;
+            copy_entry(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+
+    let is_archive = matches!(
+        src.extension().and_then(|e| e.to_str()),
+        Some("jar") | Some("zip")
+    );
+    if !is_archive || fs::hard_link(src, dst).is_err() {
+        fs::copy(src, dst).with_path(src)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TestDir;
+
+    /// A pack in a temp folder for one test, with a mod and a config in it
+    fn test_pack(name: &str) -> (TestDir, PathBuf) {
+        let dir = TestDir::new(&format!("snapshot-{}", name));
+        let pack = dir.join("OG3 1.0.0");
+        fs::create_dir_all(pack.join("mods")).unwrap();
+        fs::create_dir_all(pack.join("config")).unwrap();
+        fs::write(pack.join("mods").join("a.jar"), "a").unwrap();
+        fs::write(pack.join("config").join("x.toml"), "x = 1").unwrap();
+        (dir, pack)
+    }
+
+    /// Every file in a folder and its contents
+    fn contents(dir: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+        let mut files = BTreeMap::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(contents(&path));
+            } else {
+                files.insert(path.clone(), fs::read(&path).unwrap());
+            }
+        }
+        files
+    }
+
+    fn take(pack: &Path, version: &str, keep: usize) -> Snapshot {
+        Snapshot::take(pack, version, keep, &[], &BTreeMap::new(), &[]).unwrap()
+    }
+
+    #[test]
+    fn only_the_newest_snapshots_are_kept() {
+        let (_dir, pack) = test_pack("keep");
+        // older snapshots, written by hand so they are not taken in the same second
+        for (created, version) in [(1, "0.8.0"), (2, "0.9.0")] {
+            let path = snapshots_dir(&pack).join(format!("{}-{}", created, version));
+            fs::create_dir_all(&path).unwrap();
+            let info = format!(r#"{{ "version": "{}", "created": {} }}"#, version, created);
+            fs::write(path.join(INFO_FILE), info).unwrap();
+        }
+
+        take(&pack, "1.0.0", 2);
+
+        let versions: Vec<String> = Snapshot::list(&pack)
+            .into_iter()
+            .map(|snapshot| snapshot.version)
+            .collect();
+        assert_eq!(versions, ["1.0.0", "0.9.0"]);
+        assert!(!snapshots_dir(&pack).join("1-0.8.0").exists());
+    }
+
+    #[test]
+    fn restore_puts_the_pack_back_and_deletes_the_snapshot() {
+        let (_dir, pack) = test_pack("restore");
+        let before = contents(&pack);
+        let snapshot = take(&pack, "1.0.0", 3);
+
+        // an update that replaces files rather than writing through the hard links, like the updater
+        fs::remove_file(pack.join("mods").join("a.jar")).unwrap();
+        fs::write(pack.join("mods").join("b.jar"), "b").unwrap();
+        fs::remove_file(pack.join("config").join("x.toml")).unwrap();
+        fs::write(pack.join("config").join("x.toml"), "x = 2").unwrap();
+        fs::create_dir_all(pack.join("resourcepacks")).unwrap();
+        fs::write(pack.join("resourcepacks").join("new.zip"), "new").unwrap();
+
+        let snapshot_path = snapshot.path.clone();
+        Snapshot::latest(&pack).unwrap().restore(&pack).unwrap();
+
+        assert_eq!(contents(&pack), before);
+        // it did not exist when the snapshot was taken
+        assert!(!pack.join("resourcepacks").exists());
+        assert!(!snapshot_path.exists());
+        assert!(Snapshot::list(&pack).is_empty());
+    }
+}
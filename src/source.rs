@@ -0,0 +1,89 @@
+use crate::config::Channel;
+use crate::error::Result;
+use crate::github::{self, GitHubSource};
+use crate::instance::Instance;
+use crate::manifest::ManifestSource;
+use crate::modrinth::{self, ModrinthSource};
+use crate::release::Release;
+use serde::{Deserialize, Serialize};
+
+/// Somewhere new versions of the pack are published
+///
+/// The [Updater](crate::Updater) only talks to a source through this trait, so a new backend only needs an implementation
+/// and a [SourceConfig] variant
+pub trait ReleaseSource: Send + Sync {
+    /// A short name for the source shown in messages, e.g. "GitHub JMBROGB666/The-OG3-Pack-1.20.1"
+    fn name(&self) -> String;
+
+    /// Gets the newest [Release] of the pack the source was built for
+    fn latest(&self, client: &reqwest::blocking::Client) -> Result<Release>;
+
+    /// The name the source would give the installed version, so it can be compared with [Release::tag_name]
+    ///
+    /// Only used until the updater has installed a release from the source and saved its tag
+    fn installed_tag(&self, installed_version: &str) -> Result<String> {
+        Ok(installed_version.to_string())
+    }
+}
+
+/// Which [ReleaseSource] the updater uses and its settings, saved in the [Config](crate::Config)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SourceConfig {
+    /// The releases of a GitHub repository
+    GitHub {
+        owner: String,
+        repo: String,
+        /// The API to use, can be pointed at a local mock for testing
+        #[serde(default = "default_github_api")]
+        api_url: String,
+    },
+    /// The versions of a Modrinth project, by its slug or id
+    Modrinth {
+        project: String,
+        /// The API to use, can be pointed at a local mock for testing
+        #[serde(default = "default_modrinth_api")]
+        api_url: String,
+    },
+    /// A [Manifest](crate::manifest::Manifest) at a URL, e.g. one hosted on the pack's site
+    Manifest { url: String },
+}
+
+fn default_github_api() -> String {
+    github::API_URL.to_string()
+}
+
+fn default_modrinth_api() -> String {
+    modrinth::API_URL.to_string()
+}
+
+impl Default for SourceConfig {
+    fn default() -> Self {
+        SourceConfig::GitHub {
+            owner: "JMBROGB666".to_string(),
+            repo: "The-OG3-Pack-1.20.1".to_string(),
+            api_url: default_github_api(),
+        }
+    }
+}
+
+impl SourceConfig {
+    /// Creates the [ReleaseSource] for these settings, finding releases on `channel`
+    ///
+    /// Sources that have versions for more than one Minecraft version pick the one for the `instance`
+    pub fn build(&self, instance: &Instance, channel: Channel) -> Box<dyn ReleaseSource> {
+        match self {
+            SourceConfig::GitHub {
+                owner,
+                repo,
+                api_url,
+            } => Box::new(GitHubSource::new(owner, repo, api_url).with_channel(channel)),
+            SourceConfig::Modrinth { project, api_url } => Box::new(
+                ModrinthSource::new(project, api_url)
+                    .with_instance(instance.game_version(), instance.loader())
+                    .with_channel(channel),
+            ),
+            SourceConfig::Manifest { url } => Box::new(ManifestSource::new(url)),
+        }
+    }
+}
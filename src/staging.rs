@@ -0,0 +1,343 @@
+use crate::error::{PathContext, Result};
+use std::fs;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Added to the pack folder name to get the staging folder next to it
+const STAGING_SUFFIX: &str = ".og3-staging";
+/// Added to the staging folder name once an update is committed, so a crash while deleting it does not look like an interrupted swap
+const TRASH_SUFFIX: &str = ".old";
+/// Lists the entries that did not exist in the pack before they were swapped in
+const CREATED_JOURNAL: &str = "created.txt";
+
+/// A copy of the parts of the pack an update changes, kept in a folder next to the pack
+///
+/// The update is applied to the copy and only swapped into the pack once everything succeeded,
+/// so a failed or cancelled update leaves the pack as it was.
+pub struct Staging {
+    pack_location: PathBuf,
+    root: PathBuf,
+    /// The top level files and folders of the pack that get swapped
+    entries: Vec<String>,
+}
+
+impl Staging {
+    /// Creates the staging folder with a copy of the `entries` of the pack that the update changes
+    ///
+    /// Entries listed in `fresh` start out empty instead of being copied.
+    /// Files are hard linked where possible so the copy is quick and takes no extra space.
+    pub fn create(pack_location: &Path, entries: Vec<String>, fresh: &[&str]) -> Result<Self> {
+        Self::recover(pack_location)?;
+
+        let staging = Self {
+            pack_location: pack_location.to_path_buf(),
+            root: staging_root(pack_location),
+            entries,
+        };
+        let staged = staging.path();
+        fs::create_dir_all(&staged).with_path(&staged)?;
+
+        for name in &staging.entries {
+            let current = pack_location.join(name);
+            if fresh.contains(&name.as_str()) || !current.exists() {
+                continue;
+            }
+            if let Err(e) = link_or_copy(&current, &staged.join(name)) {
+                staging.discard();
+                return Err(e);
+            }
+        }
+
+        Ok(staging)
+    }
+
+    /// The staged copy of the pack, the update is applied to this instead of the pack
+    pub fn path(&self) -> PathBuf {
+        self.root.join("new")
+    }
+
+    /// Swaps the staged entries into the pack, putting everything back if any of them fail
+    pub fn swap(self) -> Result<()> {
+        let backup = self.root.join("backup");
+        fs::create_dir_all(&backup).with_path(&backup)?;
+
+        for name in &self.entries {
+            if let Err(e) = self.swap_entry(name, &backup) {
+                eprintln!("Swap failed, restoring previous pack: {}", e);
+                Self::recover(&self.pack_location)?;
+                return Err(e);
+            }
+        }
+
+        // commit by moving the staging folder out of the way in one step, then delete it
+        let trash = trash_root(&self.pack_location);
+        fs::rename(&self.root, &trash).with_path(&self.root)?;
+        let _ = fs::remove_dir_all(&trash);
+
+        Ok(())
+    }
+
+    /// Deletes the staging folder without touching the pack
+    pub fn discard(self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+
+    /// Moves the current entry to the backup and the staged one into the pack
+    fn swap_entry(&self, name: &str, backup: &Path) -> Result<()> {
+        let current = self.pack_location.join(name);
+        let staged = self.path().join(name);
+
+        if current.exists() {
+            fs::rename(&current, backup.join(name)).with_path(&current)?;
+        } else {
+            let journal = self.root.join(CREATED_JOURNAL);
+            let mut created = fs::read_to_string(&journal).unwrap_or_default();
+            created.push_str(name);
+            created.push('\n');
+            fs::write(&journal, created).with_path(&journal)?;
+        }
+
+        if staged.exists() {
+            fs::rename(&staged, &current).with_path(&staged)?;
+        }
+
+        Ok(())
+    }
+
+    /// Puts the pack back how it was if an update was interrupted while swapping, and cleans up leftover staging folders
+    pub fn recover(pack_location: &Path) -> Result<()> {
+        let trash = trash_root(pack_location);
+        if trash.exists() {
+            let _ = fs::remove_dir_all(&trash);
+        }
+
+        let root = staging_root(pack_location);
+        if !root.exists() {
+            return Ok(());
+        }
+
+        // remove entries that were new in the update
+        if let Ok(created) = fs::read_to_string(root.join(CREATED_JOURNAL)) {
+            for name in created.lines() {
+                remove_entry(&pack_location.join(name))?;
+            }
+        }
+
+        // anything in the backup was moved out of the pack, so move it back
+        let backup = root.join("backup");
+        if backup.exists() {
+            for entry in fs::read_dir(&backup).with_path(&backup)? {
+                let entry = entry.with_path(&backup)?;
+                let current = pack_location.join(entry.file_name());
+                println!("Restoring {:?}", current);
+
+                // the new version was already swapped in
+                remove_entry(&current)?;
+                fs::rename(entry.path(), &current).with_path(&current)?;
+            }
+        }
+
+        fs::remove_dir_all(&root).with_path(&root)
+    }
+}
+
+/// Creates a file in the staged pack, removing it first so a file hard linked with the pack is not changed
+pub fn create_file(path: &Path) -> Result<File> {
+    if path.exists() {
+        fs::remove_file(path).with_path(path)?;
+    }
+    File::create(path).with_path(path)
+}
+
+/// The staging folder for a pack
+fn staging_root(pack_location: &Path) -> PathBuf {
+    let mut name = pack_location.file_name().unwrap_or_default().to_os_string();
+    name.push(STAGING_SUFFIX);
+    pack_location.with_file_name(name)
+}
+
+/// Where the staging folder is moved to once an update is committed
+fn trash_root(pack_location: &Path) -> PathBuf {
+    let mut root = staging_root(pack_location).into_os_string();
+    root.push(TRASH_SUFFIX);
+    PathBuf::from(root)
+}
+
+/// Removes a file or folder if it exists
+fn remove_entry(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path).with_path(path)
+    } else if path.exists() {
+        fs::remove_file(path).with_path(path)
+    } else {
+        Ok(())
+    }
+}
+
+/// Hard links a file or all files in a folder to `dst`, copying them if hard links are not supported
+pub(crate) fn link_or_copy(src: &Path, dst: &Path) -> Result<()> {
+    if src.is_dir() {
+        fs::create_dir_all(dst).with_path(dst)?;
+        for entry in fs::read_dir(src).with_path(src)? {
+            let entry = entry.with_path(src)?;
+            link_or_copy(&entry.path(), &dst.join(entry.file_name()))?;
+        }
+    } else if fs::hard_link(src, dst).is_err() {
+        fs::copy(src, dst).with_path(src)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp_dir::TestDir;
+    use std::collections::BTreeMap;
+    use std::io::Write;
+
+    /// A pack in a temp folder for one test, with a few files in it
+    fn test_pack(name: &str) -> (TestDir, PathBuf) {
+        let dir = TestDir::new(&format!("staging-{}", name));
+        let pack = dir.join("OG3 1.0.0");
+        fs::create_dir_all(pack.join("mods")).unwrap();
+        fs::create_dir_all(pack.join("config").join("sub")).unwrap();
+        fs::write(pack.join("mods").join("a.jar"), "a").unwrap();
+        fs::write(pack.join("mods").join("b.jar"), "b").unwrap();
+        fs::write(pack.join("config").join("sub").join("x.toml"), "x").unwrap();
+        fs::write(pack.join("options.txt"), "options").unwrap();
+        fs::write(pack.join("notes.txt"), "notes").unwrap();
+        (dir, pack)
+    }
+
+    /// Every file in a folder and its contents, to check a pack is byte for byte the same
+    fn contents(dir: &Path) -> BTreeMap<PathBuf, Vec<u8>> {
+        let mut files = BTreeMap::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.insert(path.clone(), Vec::new());
+                files.extend(contents(&path));
+            } else {
+                files.insert(path.clone(), fs::read(&path).unwrap());
+            }
+        }
+        files
+    }
+
+    /// Changes the staged copy the way an update does, without writing through hard links
+    fn change_staged(staged: &Path) {
+        let write = |path: PathBuf, contents: &[u8]| {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            create_file(&path).unwrap().write_all(contents).unwrap();
+        };
+        write(staged.join("mods").join("a.jar"), b"new a");
+        let _ = fs::remove_file(staged.join("mods").join("b.jar"));
+        write(staged.join("mods").join("c.jar"), b"c");
+        write(staged.join("config").join("sub").join("x.toml"), b"new x");
+        write(staged.join("kubejs").join("a.js"), b"js");
+    }
+
+    fn entries(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn swap_installs_the_staged_entries() {
+        let (_dir, pack) = test_pack("swap");
+        let staging = Staging::create(&pack, entries(&["mods", "config", "kubejs"]), &[]).unwrap();
+        change_staged(&staging.path());
+        staging.swap().unwrap();
+
+        assert_eq!(fs::read(pack.join("mods").join("a.jar")).unwrap(), b"new a");
+        assert!(!pack.join("mods").join("b.jar").exists());
+        assert_eq!(fs::read(pack.join("mods").join("c.jar")).unwrap(), b"c");
+        assert_eq!(
+            fs::read(pack.join("config").join("sub").join("x.toml")).unwrap(),
+            b"new x"
+        );
+        assert_eq!(fs::read(pack.join("kubejs").join("a.js")).unwrap(), b"js");
+        assert_eq!(fs::read(pack.join("options.txt")).unwrap(), b"options");
+        assert!(!staging_root(&pack).exists());
+        assert!(!trash_root(&pack).exists());
+    }
+
+    #[test]
+    fn failed_swap_leaves_the_pack_unchanged() {
+        let (_dir, pack) = test_pack("failed-swap");
+        let before = contents(&pack);
+
+        let staging = Staging::create(
+            &pack,
+            entries(&["mods", "config", "kubejs", "notes.txt/inner"]),
+            &[],
+        )
+        .unwrap();
+        change_staged(&staging.path());
+        // notes.txt is a file in the pack, so nothing can be moved to notes.txt/inner
+        fs::create_dir_all(staging.path().join("notes.txt").join("inner")).unwrap();
+
+        assert!(staging.swap().is_err());
+        assert_eq!(contents(&pack), before);
+        assert!(!staging_root(&pack).exists());
+    }
+
+    #[test]
+    fn recover_undoes_an_interrupted_swap() {
+        let (_dir, pack) = test_pack("recover");
+        let before = contents(&pack);
+
+        let staging = Staging::create(&pack, entries(&["mods", "config", "kubejs"]), &[]).unwrap();
+        change_staged(&staging.path());
+
+        // crash after swapping mods and kubejs, halfway through config
+        let backup = staging.root.join("backup");
+        fs::create_dir_all(&backup).unwrap();
+        staging.swap_entry("mods", &backup).unwrap();
+        staging.swap_entry("kubejs", &backup).unwrap();
+        fs::rename(pack.join("config"), backup.join("config")).unwrap();
+        drop(staging);
+
+        Staging::recover(&pack).unwrap();
+
+        assert_eq!(contents(&pack), before);
+        assert!(!pack.join("kubejs").exists());
+        assert!(!staging_root(&pack).exists());
+    }
+
+    #[test]
+    fn recover_removes_a_committed_update_left_in_the_trash() {
+        let (_dir, pack) = test_pack("trash");
+        let staging = Staging::create(&pack, entries(&["mods"]), &[]).unwrap();
+        change_staged(&staging.path());
+        staging.swap().unwrap();
+        let after = contents(&pack);
+
+        // crash while deleting the trash
+        fs::create_dir_all(trash_root(&pack).join("backup").join("mods")).unwrap();
+        fs::write(
+            trash_root(&pack).join("backup").join("mods").join("a.jar"),
+            "a",
+        )
+        .unwrap();
+
+        Staging::recover(&pack).unwrap();
+
+        assert_eq!(contents(&pack), after);
+        assert!(!trash_root(&pack).exists());
+    }
+
+    #[test]
+    fn discard_leaves_the_pack_unchanged() {
+        let (_dir, pack) = test_pack("discard");
+        let before = contents(&pack);
+
+        let staging =
+            Staging::create(&pack, entries(&["mods", "config", "kubejs"]), &["mods"]).unwrap();
+        change_staged(&staging.path());
+        staging.discard();
+
+        assert_eq!(contents(&pack), before);
+        assert!(!staging_root(&pack).exists());
+    }
+}
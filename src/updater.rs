@@ -0,0 +1,1206 @@
+use crate::config::{Config, Side};
+use crate::disk::check_space;
+use crate::download::{blocked_reason, download_resumable, part_path, Expected};
+use crate::error::{PathContext, Result, UpdateError};
+use crate::hash::{
+    compute_hashes_for_file, find_mismatch, get_all_files_with_hashes, matches_strongest,
+};
+use crate::index::MrpackIndex;
+use crate::instance::{Instance, VersionMismatch};
+use crate::paths::safe_relative_path;
+use crate::progress::{Phase, Progress, Tracker};
+use crate::release::{Asset, Release};
+use crate::snapshot::Snapshot;
+use crate::source::ReleaseSource;
+use crate::staging::{create_file, link_or_copy, Staging};
+use crate::{format_size, FileInfo};
+use rayon::prelude::*;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Cursor;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use zip::ZipArchive;
+
+/// How many times each download URL is tried before falling back to the next one
+const DOWNLOAD_ATTEMPTS: usize = 2;
+
+/// The result of [Updater::check]
+#[derive(Debug, Clone)]
+pub struct UpdateCheck {
+    /// The installed version formatted like a release tag e.g. "v1.2"
+    pub installed_version: String,
+    /// The latest release of the pack
+    pub release: Release,
+}
+
+impl UpdateCheck {
+    /// If the latest release is different from the installed version
+    pub fn update_available(&self) -> bool {
+        self.installed_version != self.release.tag_name
+    }
+}
+
+/// A file the index marks as optional, the user picks if it is installed
+#[derive(Debug, Clone)]
+pub struct OptionalFile {
+    /// Where the file goes inside the pack
+    pub path: String,
+    /// The size of the file in bytes
+    pub size: u64,
+    /// If the file will be installed
+    pub install: bool,
+    /// If the user has not made a choice for this file yet
+    pub new: bool,
+}
+
+/// Everything that needs to change to update the pack, created by [Updater::plan]
+///
+/// Nothing is changed until the plan is passed to [Updater::apply], so it can be shown to the user first
+pub struct UpdatePlan {
+    /// The raw .mrpack file, the overrides are extracted from this
+    mrpack: Vec<u8>,
+    /// The side the plan was made for
+    side: Side,
+    /// The version of the pack being installed
+    pub new_version: String,
+    /// If the whole mods folder gets replaced
+    pub redownload_all: bool,
+    /// Installed files that will be deleted, named by their path inside the pack
+    pub to_delete: Vec<FileInfo>,
+    /// Files from the index that will be downloaded, named by their path inside the pack
+    ///
+    /// Files already in the download cache are copied from there instead
+    pub to_download: Vec<FileInfo>,
+    /// The files in [UpdatePlan::to_download] that are not in the cache, they need an internet connection
+    pub not_cached: Vec<String>,
+    /// Files from the overrides that will be written into the pack, named by their path inside the pack
+    pub to_overwrite: Vec<FileInfo>,
+    /// The optional files in the index and if they will be installed
+    pub optional: Vec<OptionalFile>,
+    /// Minecraft and loader versions the new version needs that the instance does not have
+    pub version_mismatches: Vec<VersionMismatch>,
+    /// If the instance is switched to the needed versions when the plan is applied
+    pub can_update_instance: bool,
+    /// The `dependencies` of the new version's index
+    dependencies: HashMap<String, String>,
+    /// The paths of the index files the new version installs
+    installed_files: Vec<String>,
+    /// The name of the source the new version is from and its release tag, `None` for a local file
+    release_tag: Option<(String, String)>,
+    /// The top level files and folders of the pack that the update changes
+    managed: Vec<String>,
+}
+
+impl UpdatePlan {
+    /// The total size of the files that will be deleted
+    pub fn delete_size(&self) -> u64 {
+        total_size(&self.to_delete)
+    }
+
+    /// The total size of the files that will be downloaded, files without a known size count as 0
+    pub fn download_size(&self) -> u64 {
+        total_size(&self.to_download)
+    }
+
+    /// The total size of the files extracted from the overrides
+    pub fn overwrite_size(&self) -> u64 {
+        total_size(&self.to_overwrite)
+    }
+
+    /// Warnings for the user about [UpdatePlan::version_mismatches], empty if there are none
+    pub fn version_warnings(&self) -> Vec<String> {
+        if self.version_mismatches.is_empty() {
+            return Vec::new();
+        }
+
+        let mut warnings: Vec<String> = self
+            .version_mismatches
+            .iter()
+            .map(|mismatch| mismatch.describe())
+            .collect();
+        if self.can_update_instance {
+            warnings.push("The instance will be switched to these versions".to_string());
+        } else {
+            warnings.push(
+                "Change the instance to these versions in the launcher or the mods will not load"
+                    .to_string(),
+            );
+        }
+        warnings
+    }
+
+    /// A warning listing the files that can not be installed without an internet connection, empty if there are none
+    pub fn offline_warning(&self) -> String {
+        if self.not_cached.is_empty() {
+            return String::new();
+        }
+        format!(
+            "{} files are not in the cache and need an internet connection to install:\n{}",
+            self.not_cached.len(),
+            self.not_cached.join("\n")
+        )
+    }
+
+    /// If the index has optional files the user has not been asked about
+    pub fn has_new_optional(&self) -> bool {
+        self.optional.iter().any(|file| file.new)
+    }
+
+    /// If applying the plan would not change anything
+    pub fn is_empty(&self) -> bool {
+        self.to_delete.is_empty() && self.to_download.is_empty() && self.to_overwrite.is_empty()
+    }
+
+    /// A one line summary of the plan with the number of files and their sizes
+    pub fn summary(&self) -> String {
+        format!(
+            "Delete {} files ({}), download {} files ({}), extract {} files ({})",
+            self.to_delete.len(),
+            format_size(self.delete_size()),
+            self.to_download.len(),
+            format_size(self.download_size()),
+            self.to_overwrite.len(),
+            format_size(self.overwrite_size()),
+        )
+    }
+
+    /// One line per file that will be changed, with what happens to it and its size
+    pub fn describe(&self) -> Vec<String> {
+        let line = |action: &str, file: &FileInfo| match file.size {
+            Some(size) => format!("{} {} ({})", action, file.name, format_size(size)),
+            None => format!("{} {}", action, file.name),
+        };
+
+        let deletes = self.to_delete.iter().map(|file| line("Delete", file));
+        let downloads = self.to_download.iter().map(|file| {
+            if self.not_cached.contains(&file.name) {
+                line("Download", file)
+            } else {
+                line("Copy from cache", file)
+            }
+        });
+        let overwrites = self.to_overwrite.iter().map(|file| line("Extract", file));
+
+        deletes.chain(downloads).chain(overwrites).collect()
+    }
+}
+
+/// Adds up the sizes of a list of [FileInfo]
+fn total_size(files: &[FileInfo]) -> u64 {
+    files.iter().filter_map(|file| file.size).sum()
+}
+
+/// Runs the stages of an update against the pack in a [Config]
+pub struct Updater {
+    config: Config,
+    config_path: PathBuf,
+    cache_dir: PathBuf,
+    client: reqwest::blocking::Client,
+    /// Where new versions are checked for in the order they are tried, from the [Config] unless set with [Updater::with_source]
+    sources: Vec<Box<dyn ReleaseSource>>,
+    /// Set to stop [Updater::apply], the pack is left as it was
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Kills the Modrinth App to stop it from messing with the mods
+pub fn close_modrinth_app() {
+    #[cfg(target_os = "windows")]
+    println!(
+        "{:#?}",
+        std::process::Command::new("taskkill")
+            .args(["/F", "/IM", "Modrinth App.exe"])
+            .output()
+    );
+}
+
+impl Updater {
+    /// Creates an [Updater] that saves the config back to `config_path` once an update is applied
+    pub fn new<P: Into<PathBuf>>(config: Config, config_path: P) -> Self {
+        let instance = Instance::load(&config.pack_location, &config.installed_dependencies);
+        Self {
+            sources: std::iter::once(&config.source)
+                .chain(&config.fallback_sources)
+                .map(|source| source.build(&instance, config.channel))
+                .collect(),
+            config,
+            config_path: config_path.into(),
+            cache_dir: PathBuf::from("./cache"),
+            client: reqwest::blocking::Client::new(),
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Creates an [Updater] from the config file at `config_path`
+    pub fn from_config_file<P: Into<PathBuf>>(config_path: P) -> Result<Self> {
+        let config_path = config_path.into();
+        Ok(Self::new(Config::load(&config_path)?, config_path))
+    }
+
+    /// Sets the directory downloaded pack files are cached in
+    pub fn with_cache_dir<P: Into<PathBuf>>(mut self, cache_dir: P) -> Self {
+        self.cache_dir = cache_dir.into();
+        self
+    }
+
+    /// Sets where new versions are checked for instead of the sources in the [Config]
+    pub fn with_source(mut self, source: Box<dyn ReleaseSource>) -> Self {
+        self.sources = vec![source];
+        self
+    }
+
+    /// Sets the flag that cancels [Updater::apply] when it is set to true
+    pub fn with_cancel_flag(mut self, cancelled: Arc<AtomicBool>) -> Self {
+        self.cancelled = cancelled;
+        self
+    }
+
+    /// The current [Config]
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Checks the release sources for the latest release and compares it with the installed version
+    ///
+    /// The sources are tried in order, falling back to the next one when one can not be reached
+    pub fn check(&self) -> Result<UpdateCheck> {
+        let mut failures = Vec::new();
+        for source in &self.sources {
+            println!(
+                "Checking {} for {} updates",
+                source.name(),
+                self.config.channel.name()
+            );
+            let installed = match self.config.installed_tags.get(&source.name()) {
+                Some(tag) => Ok(tag.clone()),
+                None => source.installed_tag(&self.config.version),
+            };
+            let result = installed.and_then(|installed| {
+                let mut release = source.latest(&self.client)?;
+                release.source = source.name();
+                Ok((installed, release))
+            });
+            match result {
+                Ok((installed_version, release)) => {
+                    println!("latest version = {}", release.tag_name);
+                    println!("installed version = {}", installed_version);
+
+                    return Ok(UpdateCheck {
+                        installed_version,
+                        release,
+                    });
+                }
+                Err(e) => {
+                    eprintln!("{} failed: {}", source.name(), e);
+                    failures.push((source.name(), e));
+                }
+            }
+        }
+
+        // with one source its own error says the most
+        if failures.len() == 1 {
+            return Err(failures.remove(0).1);
+        }
+        Err(UpdateError::Release(format!(
+            "none of the release sources could be reached\n{}",
+            failures
+                .iter()
+                .map(|(name, e)| format!("{}: {}", name, e))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )))
+    }
+
+    /// Downloads the pack for a [Release] and works out what needs to change, nothing in the pack is modified
+    ///
+    /// If the pack can not be downloaded, the latest release of each of the other sources is tried in order
+    pub fn plan(&self, release: &Release, progress: &dyn Progress) -> Result<UpdatePlan> {
+        let tracker = Tracker::new(progress);
+
+        // download latest version of pack zip
+        let file = release.pack_asset()?;
+        let (release, buf) = match self.download_pack(&release.tag_name, file, &tracker) {
+            Ok(buf) => (release.clone(), buf),
+            // another source will not fix a problem with the disk
+            Err(e @ (UpdateError::Filesystem { .. } | UpdateError::Cancelled)) => return Err(e),
+            Err(e) => self.download_fallback_pack(file, e, &tracker)?,
+        };
+
+        println!("File size: {} bytes", buf.len());
+
+        let name = &release.pack_asset()?.name;
+        let mut plan = match self.plan_pack(name, buf, &tracker) {
+            Ok(plan) => plan,
+            Err(e @ (UpdateError::Archive(_) | UpdateError::Index(_))) => {
+                // a broken pack would be used from the cache every time, download it again next time
+                let cached = self.cache_dir.join(name);
+                println!("Deleting unreadable {:?}", cached);
+                let _ = fs::remove_file(&cached);
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        };
+        plan.release_tag = Some((release.source, release.tag_name));
+        Ok(plan)
+    }
+
+    /// Downloads the pack from the first source that has one other than `failed`, which could not be downloaded with `error`
+    ///
+    /// Returns the [Release] the pack is from along with it
+    fn download_fallback_pack(
+        &self,
+        failed: &Asset,
+        error: UpdateError,
+        tracker: &Tracker,
+    ) -> Result<(Release, Vec<u8>)> {
+        eprintln!("{} failed: {}", failed.browser_download_url, error);
+        let mut failures = vec![format!("{}: {}", failed.browser_download_url, error)];
+
+        for source in &self.sources {
+            let found = source.latest(&self.client).and_then(|mut release| {
+                release.source = source.name();
+                let asset = release.pack_asset()?.clone();
+                Ok((release, asset))
+            });
+            let (release, asset) = match found {
+                Ok(found) => found,
+                Err(e) => {
+                    eprintln!("{} failed: {}", source.name(), e);
+                    failures.push(format!("{}: {}", source.name(), e));
+                    continue;
+                }
+            };
+            // the source the failed pack came from
+            if asset.browser_download_url == failed.browser_download_url {
+                continue;
+            }
+
+            println!("Trying {} from {}", asset.name, source.name());
+            match self.download_pack(&release.tag_name, &asset, tracker) {
+                Ok(buf) => return Ok((release, buf)),
+                Err(e @ (UpdateError::Filesystem { .. } | UpdateError::Cancelled)) => {
+                    return Err(e)
+                }
+                Err(e) => {
+                    eprintln!("{} failed: {}", asset.browser_download_url, e);
+                    failures.push(format!("{}: {}", asset.browser_download_url, e));
+                }
+            }
+        }
+
+        Err(UpdateError::Download(format!(
+            "the pack could not be downloaded from any source\n{}",
+            failures.join("\n")
+        )))
+    }
+
+    /// Works out what needs to change to install a .mrpack or pack zip the user already has, e.g. from a USB stick
+    ///
+    /// Files from the index are still downloaded unless they are installed or in the cache,
+    /// [UpdatePlan::not_cached] lists the ones that need an internet connection
+    pub fn plan_local(&self, path: &Path, progress: &dyn Progress) -> Result<UpdatePlan> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !name.ends_with(".mrpack") && !name.ends_with(".zip") {
+            return Err(UpdateError::Archive(format!(
+                "{} is not a .mrpack or pack zip",
+                path.display()
+            )));
+        }
+
+        let tracker = Tracker::new(progress);
+        println!("Installing from {}", path.display());
+        let buf = fs::read(path).with_path(path)?;
+
+        self.plan_pack(&name, buf, &tracker)
+    }
+
+    /// Works out what needs to change to install the pack file `name` with the contents `buf`
+    fn plan_pack(&self, name: &str, buf: Vec<u8>, tracker: &Tracker) -> Result<UpdatePlan> {
+        // put the pack back together if the last update was interrupted, so we plan against what is really installed
+        Staging::recover(&self.config.pack_location)?;
+
+        // get [FileInfo] for all installed mods
+        tracker.phase(Phase::HashingMods, 0);
+        let mods_dir = self.mods_dir();
+        let local_modinfo: Vec<FileInfo> = if mods_dir.exists() {
+            get_all_files_with_hashes(mods_dir.clone(), tracker)
+                .with_path(&mods_dir)?
+                .into_iter()
+                .map(|file_info| FileInfo {
+                    name: format!("mods/{}", file_info.name),
+                    ..file_info
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        tracker.phase(Phase::ReadingPack, 1);
+
+        let mrpack = read_mrpack(name, buf)?;
+        let index = read_index(&mrpack)?;
+
+        tracker.advance(1);
+
+        println!("Getting files from json file");
+
+        let side = self.config.side;
+        let choices = &self.config.optional_files;
+        // downloads are always checked against the index hashes, even when every file is downloaded again
+        let index_files = index.file_infos(side, choices);
+        let installed_files = index_files
+            .iter()
+            .map(|file_info| file_info.name.clone())
+            .collect();
+
+        // refuse the whole update rather than installing part of it
+        let mut blocked: Vec<(String, String)> = index_files
+            .iter()
+            .flat_map(|file_info| &file_info.urls)
+            .filter_map(|url| {
+                let reason = blocked_reason(url, &self.config.trusted_hosts)?;
+                Some((url.clone(), reason.to_string()))
+            })
+            .collect();
+        blocked.sort();
+        blocked.dedup();
+        if !blocked.is_empty() {
+            return Err(UpdateError::BlockedDownloads(blocked));
+        }
+
+        println!("Checking what files to delete and download");
+
+        let mut to_delete = Vec::new();
+        let mut to_download = Vec::new();
+
+        // compile a list of what files to download
+        if self.config.redownload_all {
+            // the whole mods folder gets removed
+            to_delete = local_modinfo;
+            to_download = index_files;
+        } else {
+            // files outside the top of the mods folder are only looked at if the index lists them
+            let mut installed = local_modinfo.clone();
+            for file_info in &index_files {
+                let path = self.config.pack_location.join(&file_info.name);
+                if installed.iter().any(|local| local.name == file_info.name) || !path.is_file() {
+                    continue;
+                }
+                if let Ok(hashes) = compute_hashes_for_file(&path) {
+                    installed.push(FileInfo::new(
+                        file_info.name.clone(),
+                        Vec::new(),
+                        hashes,
+                        None,
+                    ));
+                }
+            }
+
+            // files match by path and the strongest hash the index gives
+            let faster_expected: HashMap<_, _> = index_files
+                .iter()
+                .map(|item| (&item.name, &item.hashes))
+                .collect();
+            let faster_installed: HashMap<_, _> = installed
+                .iter()
+                .map(|item| (&item.name, &item.hashes))
+                .collect();
+
+            for file_info in &local_modinfo {
+                // if the mod not expected to be installed
+                let expected = faster_expected.get(&file_info.name);
+                if !expected.is_some_and(|hashes| matches_strongest(hashes, &file_info.hashes)) {
+                    // this file is no longer in the modpack or the user installed the file manually, so delete it
+                    to_delete.push(file_info.clone());
+                }
+            }
+
+            for file_info in &index_files {
+                // if the file is not installed or corrupt
+                let local = faster_installed.get(&file_info.name);
+                if !local.is_some_and(|local| matches_strongest(&file_info.hashes, local)) {
+                    // add to downloads
+                    to_download.push(file_info.clone());
+                }
+            }
+        }
+
+        // files the index has but this side does not want, mods are already handled above
+        for file in index.unwanted_files(side, choices) {
+            let path = self.config.pack_location.join(&file.path);
+            let listed = to_delete.iter().any(|local| local.name == file.path);
+            if !listed && path.is_file() {
+                let size = fs::metadata(&path).map(|m| m.len()).ok();
+                to_delete.push(FileInfo::new(
+                    file.path.clone(),
+                    Vec::new(),
+                    Vec::new(),
+                    size,
+                ));
+            }
+        }
+
+        // files an earlier version installed that the new index no longer has, e.g. a config file it dropped
+        for path in &self.config.installed_files {
+            let in_index = index.files.iter().any(|file| &file.path == path);
+            let listed = to_delete.iter().any(|local| &local.name == path);
+            if in_index || listed {
+                continue;
+            }
+            let Ok(relative) = safe_relative_path(path, path) else {
+                continue;
+            };
+            let full_path = self.config.pack_location.join(relative);
+            if full_path.is_file() {
+                let size = fs::metadata(&full_path).map(|m| m.len()).ok();
+                to_delete.push(FileInfo::new(path.clone(), Vec::new(), Vec::new(), size));
+            }
+        }
+
+        let not_cached = to_download
+            .iter()
+            .filter(|file_info| self.cached_file(file_info).is_none())
+            .map(|file_info| file_info.name.clone())
+            .collect();
+
+        let new_version = index.version_id.clone();
+
+        let overrides = override_entries(&mut ZipArchive::new(Cursor::new(&mrpack[..]))?, side)?;
+
+        let mut managed = vec!["mods".to_string()];
+        let paths = index
+            .files
+            .iter()
+            .map(|file| Path::new(&file.path))
+            .chain(overrides.iter().map(|entry| entry.path.as_path()))
+            .chain(to_delete.iter().map(|file_info| Path::new(&file_info.name)));
+        for path in paths {
+            if let Some(top) = path.components().next() {
+                let top = top.as_os_str().to_string_lossy().to_string();
+                if !managed.contains(&top) {
+                    managed.push(top);
+                }
+            }
+        }
+
+        let to_overwrite = overrides
+            .into_iter()
+            .filter(|entry| !entry.is_dir)
+            .map(|entry| {
+                FileInfo::new(
+                    entry.path.display().to_string(),
+                    Vec::new(),
+                    Vec::new(),
+                    Some(entry.size),
+                )
+            })
+            .collect();
+
+        let optional = index
+            .optional_files(side)
+            .map(|file| {
+                let choice = self.config.optional_files.get(&file.path).copied();
+                OptionalFile {
+                    path: file.path.clone(),
+                    size: file.file_size,
+                    install: choice.unwrap_or(true),
+                    new: choice.is_none(),
+                }
+            })
+            .collect();
+
+        let instance = Instance::load(
+            &self.config.pack_location,
+            &self.config.installed_dependencies,
+        );
+        let version_mismatches = instance.mismatches(&index.dependencies);
+        for mismatch in &version_mismatches {
+            println!("{}", mismatch.describe());
+        }
+
+        Ok(UpdatePlan {
+            mrpack,
+            side,
+            new_version,
+            redownload_all: self.config.redownload_all,
+            to_delete,
+            to_download,
+            not_cached,
+            to_overwrite,
+            optional,
+            version_mismatches,
+            can_update_instance: instance.can_update(),
+            dependencies: index.dependencies,
+            installed_files,
+            release_tag: None,
+            managed,
+        })
+    }
+
+    /// Applies an [UpdatePlan] to the pack and saves the new version to the config
+    ///
+    /// The update is applied to a staged copy of the pack first and only swapped in once everything is
+    /// downloaded and checked, if anything fails or the update is cancelled the pack is left as it was
+    pub fn apply(&mut self, plan: UpdatePlan, progress: &dyn Progress) -> Result<()> {
+        self.cancelled.store(false, Ordering::Relaxed);
+
+        // running out of space halfway would leave a half staged update, so check before starting
+        // downloads go to the cache first, then they and the overrides are written next to the pack
+        check_space(&self.cache_dir, plan.download_size())?;
+        check_space(
+            &self.config.pack_location,
+            plan.download_size() + plan.overwrite_size(),
+        )?;
+
+        // with redownload_all the mods folder starts out empty
+        let fresh: &[&str] = if plan.redownload_all { &["mods"] } else { &[] };
+        let staging = Staging::create(&self.config.pack_location, plan.managed.clone(), fresh)?;
+
+        let tracker = Tracker::new(progress);
+
+        if let Err(e) = self.stage(&plan, &staging.path(), &tracker) {
+            println!("Update failed, discarding staged changes");
+            staging.discard();
+            return Err(e);
+        }
+
+        tracker.phase(Phase::Installing, 3);
+
+        // save the current version so it can be restored if the update breaks something
+        if self.config.snapshots_to_keep > 0 {
+            if let Err(e) = Snapshot::take(
+                &self.config.pack_location,
+                &self.config.version,
+                self.config.snapshots_to_keep,
+                &plan.managed,
+                &self.config.installed_dependencies,
+                &self.config.installed_files,
+            ) {
+                staging.discard();
+                return Err(e);
+            }
+        }
+
+        tracker.advance(1);
+
+        staging.swap()?;
+
+        tracker.advance(1);
+
+        self.switch_instance(&plan.dependencies);
+
+        // update config with the new pack version
+        // we do this last as if the user cancels the downloads it should not break everything
+        self.config.version = plan.new_version;
+        // a tag from another source or an older version would no longer match
+        self.config.installed_tags.clear();
+        self.config.installed_tags.extend(plan.release_tag);
+        self.config.installed_files = plan.installed_files;
+        self.config.save(&self.config_path)?;
+
+        tracker.phase(Phase::Done, 0);
+
+        Ok(())
+    }
+
+    /// Saves the user's choice of optional files, true to install a file
+    ///
+    /// Plan the update again afterwards so the [UpdatePlan] follows the new choices
+    pub fn choose_optional<I: IntoIterator<Item = (String, bool)>>(
+        &mut self,
+        choices: I,
+    ) -> Result<()> {
+        self.config.optional_files.extend(choices);
+        self.config.save(&self.config_path)
+    }
+
+    /// Restores the newest [Snapshot] of the pack and returns the version that was restored
+    pub fn restore_previous(&mut self) -> Result<String> {
+        let snapshot = Snapshot::latest(&self.config.pack_location).ok_or_else(|| {
+            UpdateError::Snapshot("there is no previous version to restore".to_string())
+        })?;
+        let version = snapshot.version.clone();
+        let files = snapshot.files.clone();
+        let dependencies: HashMap<String, String> =
+            snapshot.dependencies.clone().into_iter().collect();
+
+        snapshot.restore(&self.config.pack_location)?;
+
+        // the launcher's profile is not in the snapshot, so switch it back to the restored version's loader
+        self.switch_instance(&dependencies);
+
+        self.config.version = version.clone();
+        self.config.installed_tags.clear();
+        self.config.installed_files = files;
+        self.config.save(&self.config_path)?;
+
+        Ok(version)
+    }
+
+    /// Switches the instance to the Minecraft and loader versions in `dependencies` if it can be
+    ///
+    /// Versions are saved to the config once the instance has them, or once the user has been
+    /// told to switch the instance in the launcher, as the updater can not read its versions then.
+    /// A profile that could not be changed is read again next time, so the mismatch is still reported.
+    /// The pack is already in place by now, so failing to switch the instance only gets a warning.
+    fn switch_instance(&mut self, dependencies: &HashMap<String, String>) {
+        let instance = Instance::load(
+            &self.config.pack_location,
+            &self.config.installed_dependencies,
+        );
+
+        let record = if instance.mismatches(dependencies).is_empty() {
+            true
+        } else if instance.can_update() {
+            match instance.update(dependencies) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("Could not switch the instance versions: {}", e);
+                    false
+                }
+            }
+        } else {
+            // the versions come from the config from now on, so the warning is not repeated every time
+            eprintln!("The instance has to be switched to the new versions in the launcher");
+            true
+        };
+
+        // a version from before the updater recorded them says nothing about the instance
+        if record && !dependencies.is_empty() {
+            self.config.installed_dependencies = dependencies
+                .iter()
+                .map(|(component, version)| (component.clone(), version.clone()))
+                .collect();
+        }
+    }
+
+    /// Applies an [UpdatePlan] to the staged copy of the pack at `pack` and checks the result
+    fn stage(&self, plan: &UpdatePlan, pack: &Path, tracker: &Tracker) -> Result<()> {
+        for file_info in &plan.to_delete {
+            let _ = fs::remove_file(pack.join(&file_info.name));
+        }
+
+        tracker.phase(Phase::Extracting, plan.to_overwrite.len() as u64);
+
+        extract_overrides(&plan.mrpack, pack, plan.side, &self.cancelled, tracker)?;
+
+        println!("Extracted overrides to {:?}", pack);
+
+        tracker.phase(Phase::DownloadingFiles, plan.download_size());
+
+        println!(
+            "Collected {} file URLs to download:",
+            plan.to_download.len()
+        );
+
+        // download and install files, a few at a time sharing the client's connections
+        let mods_dir = pack.join("mods");
+        fs::create_dir_all(&mods_dir).with_path(&mods_dir)?;
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.config.max_concurrent_downloads.max(1))
+            .build()
+            .map_err(|e| UpdateError::Download(e.to_string()))?;
+
+        pool.install(|| {
+            plan.to_download
+                .par_iter()
+                .try_for_each(|fileinfo| -> Result<()> {
+                    self.check_cancelled()?;
+                    tracker.file(&fileinfo.name);
+                    self.download_file(fileinfo, pack, tracker)
+                })
+        })?;
+
+        validate_downloads(&plan.to_download, pack)
+    }
+
+    /// Installs a file from the index to its path inside `pack`, from the cache if it is there or else by downloading it
+    ///
+    /// Each URL is tried [DOWNLOAD_ATTEMPTS] times before falling back to the next one.
+    /// Downloads are kept in the cache so they can be resumed by the next attempt or installed again without downloading.
+    fn download_file(&self, fileinfo: &FileInfo, pack: &Path, tracker: &Tracker) -> Result<()> {
+        let out_path = pack.join(&fileinfo.name);
+
+        if let Some(cached) = self.cached_file(fileinfo) {
+            println!("Copying {} from the cache", fileinfo.name);
+            install_cached(&cached, &out_path)?;
+            tracker.advance(fileinfo.size.unwrap_or_default());
+            return Ok(());
+        }
+
+        if fileinfo.urls.is_empty() {
+            return Err(UpdateError::Index(format!(
+                "{} has no download URL",
+                fileinfo.name
+            )));
+        }
+
+        let cache_path = self.cache_dir.join(&fileinfo.name);
+        let part = part_path(&cache_path);
+        let expected = Expected {
+            hashes: &fileinfo.hashes,
+            size: fileinfo.size,
+        };
+        let mut failures = Vec::new();
+
+        for url in &fileinfo.urls {
+            for attempt in 1..=DOWNLOAD_ATTEMPTS {
+                self.check_cancelled()?;
+
+                println!(
+                    "Downloading {} to {:?} (attempt {})",
+                    url, out_path, attempt
+                );
+                let counted = Cell::new(0);
+                let result = download_resumable(
+                    &self.client,
+                    url,
+                    &part,
+                    &cache_path,
+                    expected,
+                    &self.cancelled,
+                    &|bytes| {
+                        counted.set(counted.get() + bytes);
+                        tracker.advance(bytes);
+                    },
+                );
+                if result.is_err() {
+                    // the next attempt counts whatever is kept in the part file again
+                    tracker.rewind(counted.get());
+                }
+                match result {
+                    Ok(()) => return install_cached(&cache_path, &out_path),
+                    // another URL will not fix a problem with the disk
+                    Err(e @ UpdateError::Filesystem { .. }) => return Err(e),
+                    Err(e @ UpdateError::Cancelled) => return Err(e),
+                    Err(e) => {
+                        eprintln!("{} failed: {}", url, e);
+                        failures.push(format!("{}: {}", url, e));
+                    }
+                }
+            }
+        }
+
+        Err(UpdateError::Download(format!(
+            "{} could not be downloaded from any of its URLs\n{}",
+            fileinfo.name,
+            failures.join("\n")
+        )))
+    }
+
+    /// The copy of a file from the index in the download cache, if there is one that matches its hashes and size
+    ///
+    /// Files without hashes are never taken from the cache as there is nothing to check them against
+    fn cached_file(&self, fileinfo: &FileInfo) -> Option<PathBuf> {
+        if fileinfo.hashes.is_empty() {
+            return None;
+        }
+        let path = self.cache_dir.join(&fileinfo.name);
+        let expected = Expected {
+            hashes: &fileinfo.hashes,
+            size: fileinfo.size,
+        };
+        matches_expected(&path, expected).then_some(path)
+    }
+
+    /// Returns [UpdateError::Cancelled] if the cancel flag is set
+    fn check_cancelled(&self) -> Result<()> {
+        check_cancelled(&self.cancelled)
+    }
+
+    /// The mods folder of the installed pack
+    fn mods_dir(&self) -> PathBuf {
+        self.config.pack_location.join("mods")
+    }
+
+    /// Gets the pack zip or .mrpack from the cache or downloads it
+    ///
+    /// The download goes to a `.part` file in the cache first so it can be resumed if it is interrupted,
+    /// the part file is named after the release `tag` so a part of another version is never resumed
+    fn download_pack(&self, tag: &str, asset: &Asset, tracker: &Tracker) -> Result<Vec<u8>> {
+        tracker.phase(Phase::DownloadingPack, asset.size.unwrap_or_default());
+        tracker.file(&asset.name);
+
+        //check if we have the file
+        let file_path = self.cache_dir.join(&asset.name);
+        let expected = Expected {
+            hashes: &asset.hashes,
+            size: asset.size,
+        };
+
+        // sources can use the same name for every version, so the cached file has to match this one
+        if matches_expected(&file_path, expected) {
+            println!("Using cached version of {}", asset.name);
+        } else {
+            if file_path.exists() {
+                println!("Cached {} is a different version", asset.name);
+                fs::remove_file(&file_path).with_path(&file_path)?;
+            }
+            println!("Downloading: {}", asset.name);
+
+            // parts of other versions can not be resumed any more
+            let tag: String = tag
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            let part = part_path(&self.cache_dir.join(format!("{}.{}", asset.name, tag)));
+            let part_name = part
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let stale_prefix = format!("{}.", asset.name);
+            for entry in fs::read_dir(&self.cache_dir)
+                .into_iter()
+                .flatten()
+                .flatten()
+            {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let is_part = name.ends_with(".part") || name.ends_with(".part.validator");
+                // the validator of this part starts with its name too
+                if is_part && name.starts_with(&stale_prefix) && !name.starts_with(&part_name) {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+
+            download_resumable(
+                &self.client,
+                &asset.browser_download_url,
+                &part,
+                &file_path,
+                expected,
+                &self.cancelled,
+                &|bytes| tracker.advance(bytes),
+            )?;
+        }
+
+        fs::read(&file_path).with_path(&file_path)
+    }
+}
+
+/// If the file at `path` exists and has the size and hashes it is `expected` to have
+fn matches_expected(path: &Path, expected: Expected) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
+    if expected.size.is_some_and(|size| size != metadata.len()) {
+        return false;
+    }
+    compute_hashes_for_file(path)
+        .is_ok_and(|hashes| find_mismatch(expected.hashes, &hashes).is_none())
+}
+
+/// Puts a file from the download cache at `out_path` in the staged pack
+fn install_cached(cached: &Path, out_path: &Path) -> Result<()> {
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent).with_path(parent)?;
+    }
+    // the staged file may be hard linked with the pack, so remove it instead of copying over it
+    if out_path.exists() {
+        fs::remove_file(out_path).with_path(out_path)?;
+    }
+    link_or_copy(cached, out_path)
+}
+
+/// Finds and reads the .mrpack file inside of the pack zip, `name` is the name of the downloaded file
+///
+/// Sources that give the .mrpack itself have nothing to unpack
+fn read_mrpack(name: &str, zip: Vec<u8>) -> Result<Vec<u8>> {
+    if name.ends_with(".mrpack") {
+        return Ok(zip);
+    }
+
+    let mut archive = ZipArchive::new(Cursor::new(zip))?;
+
+    let mut mrpack_data = Vec::new();
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file.name().ends_with(".mrpack") {
+            file.read_to_end(&mut mrpack_data)
+                .map_err(|e| UpdateError::Archive(e.to_string()))?;
+            println!(
+                "Found .mrpack file: {} size: {} bytes",
+                file.name(),
+                mrpack_data.len()
+            );
+            break;
+        }
+    }
+
+    if mrpack_data.is_empty() {
+        return Err(UpdateError::Archive(
+            "No .mrpack file found in the archive".to_string(),
+        ));
+    }
+
+    Ok(mrpack_data)
+}
+
+/// Gets the modrinth.index.json file from the .mrpack
+fn read_index(mrpack: &[u8]) -> Result<MrpackIndex> {
+    let mut archive = ZipArchive::new(Cursor::new(mrpack))?;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file.name() == "modrinth.index.json" {
+            let mut contents = String::new();
+            file.read_to_string(&mut contents)
+                .map_err(|e| UpdateError::Index(e.to_string()))?;
+            return MrpackIndex::parse(&contents);
+        }
+    }
+
+    Err(UpdateError::Index(
+        "modrinth.index.json not found in the .mrpack".to_string(),
+    ))
+}
+
+/// A file or folder in the overrides of the .mrpack that gets extracted into the pack
+struct OverrideEntry {
+    /// The index of the entry in the .mrpack
+    index: usize,
+    /// Where the entry goes, relative to the pack location
+    path: PathBuf,
+    is_dir: bool,
+    /// The uncompressed size of the entry
+    size: u64,
+}
+
+/// Finds the files and folders in the overrides of the .mrpack for `side`
+///
+/// Everything in `overrides` is used, then `client-overrides` or `server-overrides` which win
+/// over `overrides` when both have the same path
+fn override_entries<R: Read + std::io::Seek>(
+    archive: &mut ZipArchive<R>,
+    side: Side,
+) -> Result<Vec<OverrideEntry>> {
+    let side_folder = match side {
+        Side::Client => "client-overrides",
+        Side::Server => "server-overrides",
+    };
+
+    let mut entries: Vec<OverrideEntry> = Vec::new();
+    let mut side_entries = Vec::new();
+
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        let name = file.name().to_string();
+
+        // only entries in the override folders are extracted, so only they need checking
+        let folder = name.split(['/', '\\']).next().unwrap_or_default();
+        if folder != "overrides" && folder != side_folder {
+            continue;
+        }
+
+        let file_path = safe_relative_path(&name, &name)?;
+        if file.is_symlink() {
+            return Err(UpdateError::UnsafePath {
+                entry: name,
+                reason: "symlinks are not allowed".to_string(),
+            });
+        }
+
+        let rel_path: PathBuf = file_path.components().skip(1).collect();
+        if rel_path.as_os_str().is_empty() {
+            continue;
+        }
+
+        let entry = OverrideEntry {
+            index: i,
+            path: rel_path,
+            is_dir: file.is_dir(),
+            size: file.size(),
+        };
+        if folder == "overrides" {
+            entries.push(entry);
+        } else {
+            side_entries.push(entry);
+        }
+    }
+
+    for entry in side_entries {
+        match entries.iter_mut().find(|e| e.path == entry.path) {
+            Some(existing) => *existing = entry,
+            None => entries.push(entry),
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Installs the overrides of the .mrpack for `side` into the pack
+fn extract_overrides(
+    mrpack: &[u8],
+    pack_location: &Path,
+    side: Side,
+    cancelled: &AtomicBool,
+    tracker: &Tracker,
+) -> Result<()> {
+    let mut archive = ZipArchive::new(Cursor::new(mrpack))?;
+
+    for entry in override_entries(&mut archive, side)? {
+        check_cancelled(cancelled)?;
+
+        let mut file = archive.by_index(entry.index)?;
+        let outpath = pack_location.join(&entry.path);
+
+        if entry.is_dir {
+            fs::create_dir_all(&outpath).with_path(&outpath)?;
+        } else {
+            if let Some(parent) = outpath.parent() {
+                fs::create_dir_all(parent).with_path(parent)?;
+            }
+            tracker.file(&entry.path.display().to_string());
+            let mut outfile = create_file(&outpath)?;
+            std::io::copy(&mut file, &mut outfile).with_path(&outpath)?;
+            println!("Extracted: {:?}", outpath);
+            tracker.advance(1);
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks every downloaded file is in place in `pack` with the size from the index
+///
+/// Hashes are already checked while downloading
+fn validate_downloads(downloads: &[FileInfo], pack: &Path) -> Result<()> {
+    for file_info in downloads {
+        let path = pack.join(&file_info.name);
+        let size = fs::metadata(&path).with_path(&path)?.len();
+
+        if file_info.size.is_some_and(|expected| expected != size) {
+            return Err(UpdateError::Download(format!(
+                "{} is {} bytes but the index says {}",
+                file_info.name,
+                size,
+                file_info.size.unwrap_or_default()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns [UpdateError::Cancelled] if `cancelled` is set
+fn check_cancelled(cancelled: &AtomicBool) -> Result<()> {
+    if cancelled.load(Ordering::Relaxed) {
+        Err(UpdateError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
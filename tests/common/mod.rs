@@ -0,0 +1,118 @@
+//! A local HTTP server for the integration tests, so nothing is fetched from the internet
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread::JoinHandle;
+
+mod temp_dir;
+
+#[allow(unused_imports)]
+pub use temp_dir::TestDir;
+
+/// A response the mock server sends
+#[allow(dead_code)]
+pub struct Reply {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+#[allow(dead_code)]
+impl Reply {
+    /// A 200 response with `body`
+    pub fn ok<B: Into<Vec<u8>>>(body: B) -> Self {
+        Self::status(200, body)
+    }
+
+    /// A response with any status
+    pub fn status<B: Into<Vec<u8>>>(status: u16, body: B) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Adds a header to the response
+    pub fn header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.push((name, value.to_string()));
+        self
+    }
+}
+
+/// A request the mock server got
+#[allow(dead_code)]
+pub struct Request {
+    /// The request line, e.g. "GET /file HTTP/1.1"
+    pub line: String,
+    pub headers: Vec<(String, String)>,
+}
+
+#[allow(dead_code)]
+impl Request {
+    /// The value of a header, the name is not case sensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Sends the `replies` in order, one for each connection, and returns the server's URL
+///
+/// The thread returns the requests once every reply has been sent
+#[allow(dead_code)]
+pub fn serve(replies: Vec<Reply>) -> (String, JoinHandle<Vec<Request>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = std::thread::spawn(move || {
+        let mut requests = Vec::new();
+        for reply in replies {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let mut headers = Vec::new();
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                match header.trim_end().split_once(':') {
+                    Some((name, value)) => {
+                        headers.push((name.to_string(), value.trim().to_string()))
+                    }
+                    None => break,
+                }
+            }
+            requests.push(Request {
+                line: line.trim_end().to_string(),
+                headers,
+            });
+
+            let mut head = format!(
+                "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+                reply.status,
+                reply.body.len()
+            );
+            for (name, value) in &reply.headers {
+                head.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            head.push_str("\r\n");
+            stream.write_all(head.as_bytes()).unwrap();
+            stream.write_all(&reply.body).unwrap();
+        }
+        requests
+    });
+
+    (url, handle)
+}
+
+/// Serves `body` as JSON to one request
+#[allow(dead_code)]
+pub fn serve_json(body: &str) -> (String, JoinHandle<Vec<Request>>) {
+    serve(vec![
+        Reply::ok(body).header("Content-Type", "application/json")
+    ])
+}
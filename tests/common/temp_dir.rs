@@ -0,0 +1,31 @@
+//! A folder in the temp dir for one test, shared with the unit tests in `src`
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// An empty folder in the temp dir, deleted when it is dropped so a failing test does not leave it behind
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Creates the folder, `name` has to be different for every test in the same test binary
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("og3-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,186 @@
+//! Resumable downloads against a local server that answers Range requests in different ways
+
+mod common;
+
+use common::{serve, Reply, TestDir};
+use og3_pack_updater::download::{download_resumable, part_path, Expected};
+use og3_pack_updater::hash::Hasher;
+use og3_pack_updater::{FileHash, UpdateError};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The file being downloaded
+const BODY: &[u8] = b"the whole file";
+
+fn hashes(data: &[u8]) -> Vec<FileHash> {
+    let mut hasher = Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+/// Downloads from `url` to `dest` through its part file
+fn download(
+    url: &str,
+    dest: &Path,
+    hashes: &[FileHash],
+    size: Option<u64>,
+) -> Result<(), UpdateError> {
+    download_resumable(
+        &reqwest::blocking::Client::new(),
+        url,
+        &part_path(dest),
+        dest,
+        Expected { hashes, size },
+        &AtomicBool::new(false),
+        &|_| {},
+    )
+}
+
+#[test]
+fn partial_download_is_resumed_with_a_range_request() {
+    let dir = TestDir::new("download-resume");
+    let dest = dir.join("a.jar");
+    std::fs::write(part_path(&dest), &BODY[..4]).unwrap();
+
+    let (url, server) = serve(vec![Reply::status(206, &BODY[4..]).header(
+        "Content-Range",
+        &format!("bytes 4-{}/{}", BODY.len() - 1, BODY.len()),
+    )]);
+    download(&url, &dest, &hashes(BODY), Some(BODY.len() as u64)).unwrap();
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests[0].header("Range"), Some("bytes=4-"));
+    assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+    assert!(!part_path(&dest).exists());
+}
+
+#[test]
+fn whole_file_sent_for_a_range_request_restarts_the_download() {
+    let dir = TestDir::new("download-restart");
+    let dest = dir.join("a.jar");
+    // a part file from something else, the server ignores the range
+    std::fs::write(part_path(&dest), b"junk").unwrap();
+
+    let (url, server) = serve(vec![Reply::ok(BODY)]);
+    download(&url, &dest, &hashes(BODY), Some(BODY.len() as u64)).unwrap();
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests[0].header("Range"), Some("bytes=4-"));
+    assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+    assert!(!part_path(&dest).exists());
+}
+
+#[test]
+fn part_of_a_changed_file_is_not_resumed() {
+    let dir = TestDir::new("download-changed");
+    let dest = dir.join("a.jar");
+
+    // the first download is cut short after the start of an older version of the file
+    let (url, server) = serve(vec![
+        Reply::ok(b"OLDV".to_vec()).header("ETag", "\"v1\""),
+        Reply::ok(BODY).header("ETag", "\"v2\""),
+    ]);
+    let cancelled = AtomicBool::new(false);
+    let result = download_resumable(
+        &reqwest::blocking::Client::new(),
+        &url,
+        &part_path(&dest),
+        &dest,
+        Expected::default(),
+        &cancelled,
+        &|_| cancelled.store(true, Ordering::Relaxed),
+    );
+    assert!(
+        matches!(result, Err(UpdateError::Cancelled)),
+        "{:?}",
+        result
+    );
+    assert_eq!(std::fs::read(part_path(&dest)).unwrap(), b"OLDV");
+
+    download(&url, &dest, &[], Some(BODY.len() as u64)).unwrap();
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests[1].header("Range"), Some("bytes=4-"));
+    assert_eq!(requests[1].header("If-Range"), Some("\"v1\""));
+    assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+}
+
+#[test]
+fn range_for_a_file_of_another_size_restarts_the_download() {
+    let dir = TestDir::new("download-content-range");
+    let dest = dir.join("a.jar");
+    std::fs::write(part_path(&dest), b"OLDV").unwrap();
+
+    let (url, server) = serve(vec![
+        Reply::status(206, &b"ERSION-CONTENTS!"[..]).header("Content-Range", "bytes 4-19/20"),
+        Reply::ok(BODY),
+    ]);
+    download(&url, &dest, &hashes(BODY), Some(BODY.len() as u64)).unwrap();
+
+    let requests = server.join().unwrap();
+    assert_eq!(requests[0].header("Range"), Some("bytes=4-"));
+    assert_eq!(requests[1].header("Range"), None);
+    assert_eq!(std::fs::read(&dest).unwrap(), BODY);
+    assert!(!part_path(&dest).exists());
+}
+
+#[test]
+fn range_not_satisfiable_removes_the_part_file() {
+    let dir = TestDir::new("download-416");
+    let dest = dir.join("a.jar");
+    std::fs::write(part_path(&dest), b"too long for the file on the server").unwrap();
+
+    let (url, server) = serve(vec![Reply::status(416, "")]);
+    let result = download(&url, &dest, &hashes(BODY), Some(BODY.len() as u64));
+    server.join().unwrap();
+
+    assert!(
+        matches!(result, Err(UpdateError::Download(_))),
+        "{:?}",
+        result
+    );
+    assert!(!part_path(&dest).exists());
+    assert!(!dest.exists());
+}
+
+#[test]
+fn wrong_size_removes_the_part_file() {
+    let dir = TestDir::new("download-size");
+    let dest = dir.join("a.jar");
+
+    let (url, server) = serve(vec![Reply::ok(&BODY[..10])]);
+    let result = download(&url, &dest, &[], Some(BODY.len() as u64));
+    server.join().unwrap();
+
+    match result {
+        Err(e @ UpdateError::Download(_)) => {
+            assert!(
+                e.to_string().contains("expected 14 bytes but got 10"),
+                "{}",
+                e
+            )
+        }
+        other => panic!("expected a download error, got {:?}", other),
+    }
+    assert!(!part_path(&dest).exists());
+    assert!(!dest.exists());
+}
+
+#[test]
+fn wrong_hash_removes_the_part_file() {
+    let dir = TestDir::new("download-hash");
+    let dest = dir.join("a.jar");
+
+    let (url, server) = serve(vec![Reply::ok(b"not the file".to_vec())]);
+    let result = download(&url, &dest, &hashes(BODY), None);
+    server.join().unwrap();
+
+    match result {
+        Err(e @ UpdateError::Download(_)) => {
+            assert!(e.to_string().contains("sha512 mismatch"), "{}", e)
+        }
+        other => panic!("expected a download error, got {:?}", other),
+    }
+    assert!(!part_path(&dest).exists());
+    assert!(!dest.exists());
+}
@@ -0,0 +1,113 @@
+//! Installs versions of a pack whose indexes list different files outside the mods folder
+
+mod common;
+
+use common::TestDir;
+use og3_pack_updater::hash::Hasher;
+use og3_pack_updater::{Config, Updater};
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// The files each test version can list, by path and contents
+const FILES: [(&str, &str); 2] = [
+    ("config/old.toml", "old = true"),
+    ("config/kept.toml", "kept = true"),
+];
+
+/// A folder in the temp dir for one test, with the pack folder and cache inside it
+fn test_dir(name: &str) -> TestDir {
+    let dir = TestDir::new(&format!("installed-files-{}", name));
+    std::fs::create_dir_all(dir.join("pack")).unwrap();
+    dir
+}
+
+/// Writes a .mrpack for `version` listing the [FILES] at `paths`, and puts the files in the cache so nothing is downloaded
+fn write_mrpack(dir: &Path, version: &str, paths: &[&str]) -> PathBuf {
+    let files: Vec<String> = FILES
+        .iter()
+        .filter(|(path, _)| paths.contains(path))
+        .map(|(path, contents)| {
+            let cached = dir.join("cache").join(path);
+            std::fs::create_dir_all(cached.parent().unwrap()).unwrap();
+            std::fs::write(&cached, contents).unwrap();
+
+            let mut hasher = Hasher::new();
+            hasher.update(contents.as_bytes());
+            let sha512 = hasher.finalize()[0].value().to_string();
+            format!(
+                r#"{{ "path": "{}", "hashes": {{ "sha512": "{}" }}, "downloads": ["https://cdn.modrinth.com/{}"], "fileSize": {} }}"#,
+                path,
+                sha512,
+                path,
+                contents.len()
+            )
+        })
+        .collect();
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file("modrinth.index.json", SimpleFileOptions::default())
+        .unwrap();
+    write!(
+        writer,
+        r#"{{ "formatVersion": 1, "game": "minecraft", "versionId": "{}", "name": "OG3", "files": [{}], "dependencies": {{}} }}"#,
+        version,
+        files.join(",")
+    )
+    .unwrap();
+
+    let path = dir.join(format!("OG3 {}.mrpack", version));
+    std::fs::write(&path, writer.finish().unwrap().into_inner()).unwrap();
+    path
+}
+
+/// The [Updater] for the saved config, using the test's cache
+fn updater(dir: &Path) -> Updater {
+    Updater::from_config_file(dir.join("config.json"))
+        .unwrap()
+        .with_cache_dir(dir.join("cache"))
+}
+
+/// Installs the .mrpack at `path` and saves the config
+fn install(dir: &Path, path: &Path) {
+    let mut updater = updater(dir);
+    let plan = updater.plan_local(path, &()).unwrap();
+    updater.apply(plan, &()).unwrap();
+}
+
+#[test]
+fn files_dropped_from_the_index_are_deleted() {
+    let dir = test_dir("dropped");
+    let mut config = Config::from_pack_location(dir.join("pack"));
+    config.version = "1.0.0".to_string();
+    config.snapshots_to_keep = 0;
+    config.save(dir.join("config.json")).unwrap();
+
+    install(
+        &dir,
+        &write_mrpack(&dir, "1.1.0", &["config/old.toml", "config/kept.toml"]),
+    );
+    assert!(dir.join("pack/config/old.toml").is_file());
+
+    // a file the user added is not the updater's to delete
+    std::fs::write(dir.join("pack/config/mine.toml"), "mine = true").unwrap();
+
+    let mrpack = write_mrpack(&dir, "1.2.0", &["config/kept.toml"]);
+    let plan = updater(&dir).plan_local(&mrpack, &()).unwrap();
+    let deleted: Vec<&str> = plan
+        .to_delete
+        .iter()
+        .map(|file| file.name.as_str())
+        .collect();
+    assert_eq!(deleted, ["config/old.toml"]);
+
+    install(&dir, &mrpack);
+    assert!(!dir.join("pack/config/old.toml").exists());
+    assert!(dir.join("pack/config/kept.toml").is_file());
+    assert!(dir.join("pack/config/mine.toml").is_file());
+
+    let config = Config::load(dir.join("config.json")).unwrap();
+    assert_eq!(config.installed_files, ["config/kept.toml"]);
+}
@@ -0,0 +1,81 @@
+//! Installs a pack with overrides for both sides and checks which copy of each file ends up in the pack
+
+mod common;
+
+use common::TestDir;
+use og3_pack_updater::config::Side;
+use og3_pack_updater::{Config, Updater};
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Override entries of the test pack by their name in the .mrpack and contents
+const ENTRIES: [(&str, &str); 6] = [
+    ("overrides/config/a.toml", "common"),
+    ("overrides/options.txt", "shared"),
+    ("client-overrides/config/a.toml", "client"),
+    ("client-overrides/optionsof.txt", "client only"),
+    ("server-overrides/config/a.toml", "server"),
+    ("server-overrides/server.properties", "server only"),
+];
+
+/// Writes a .mrpack with no index files and the [ENTRIES] into `dir`
+fn write_mrpack(dir: &Path) -> PathBuf {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    writer.start_file("modrinth.index.json", options).unwrap();
+    writer
+        .write_all(br#"{ "formatVersion": 1, "game": "minecraft", "versionId": "1.1.0", "name": "OG3", "files": [], "dependencies": {} }"#)
+        .unwrap();
+    for (name, contents) in ENTRIES {
+        writer.start_file(name, options).unwrap();
+        writer.write_all(contents.as_bytes()).unwrap();
+    }
+
+    let path = dir.join("OG3 1.1.0.mrpack");
+    std::fs::write(&path, writer.finish().unwrap().into_inner()).unwrap();
+    path
+}
+
+/// Installs the test pack for `side` and returns the pack folder
+fn install(dir: &Path, side: Side) -> PathBuf {
+    let pack = dir.join("pack");
+    std::fs::create_dir_all(&pack).unwrap();
+    let mut config = Config::from_pack_location(pack.clone());
+    config.version = "1.0.0".to_string();
+    config.snapshots_to_keep = 0;
+    config.side = side;
+    let mut updater =
+        Updater::new(config, dir.join("config.json")).with_cache_dir(dir.join("cache"));
+
+    let plan = updater.plan_local(&write_mrpack(dir), &()).unwrap();
+    updater.apply(plan, &()).unwrap();
+    pack
+}
+
+fn read(pack: &Path, path: &str) -> String {
+    std::fs::read_to_string(pack.join(path)).unwrap()
+}
+
+#[test]
+fn client_overrides_win_on_a_client() {
+    let dir = TestDir::new("overrides-client");
+    let pack = install(&dir, Side::Client);
+
+    assert_eq!(read(&pack, "config/a.toml"), "client");
+    assert_eq!(read(&pack, "options.txt"), "shared");
+    assert_eq!(read(&pack, "optionsof.txt"), "client only");
+    assert!(!pack.join("server.properties").exists());
+}
+
+#[test]
+fn server_overrides_win_on_a_server() {
+    let dir = TestDir::new("overrides-server");
+    let pack = install(&dir, Side::Server);
+
+    assert_eq!(read(&pack, "config/a.toml"), "server");
+    assert_eq!(read(&pack, "options.txt"), "shared");
+    assert_eq!(read(&pack, "server.properties"), "server only");
+    assert!(!pack.join("optionsof.txt").exists());
+}
@@ -0,0 +1,189 @@
+//! Plans updates from crafted .mrpack files that try to write outside the pack
+
+mod common;
+
+use common::TestDir;
+use og3_pack_updater::release::{Asset, Release};
+use og3_pack_updater::{Config, UpdateError, UpdatePlan, Updater};
+use std::io::{Cursor, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// The name of the cached pack asset, so no download is needed
+const ASSET: &str = "OG3.zip";
+
+/// A valid index entry, so index tests only fail on the path being checked
+const GOOD_FILE: &str = r#"{
+    "path": "mods/good.jar",
+    "hashes": { "sha512": "00" },
+    "downloads": ["https://cdn.modrinth.com/good.jar"],
+    "fileSize": 1
+}"#;
+
+/// A folder in the temp dir for one test, with the pack folder and cache inside it
+fn test_dir(name: &str) -> TestDir {
+    let dir = TestDir::new(&format!("path-safety-{}", name));
+    std::fs::create_dir_all(dir.join("pack").join("mods")).unwrap();
+    std::fs::create_dir_all(dir.join("cache")).unwrap();
+    dir
+}
+
+fn index_json(files: &[&str]) -> String {
+    format!(
+        r#"{{
+            "formatVersion": 1,
+            "game": "minecraft",
+            "versionId": "1.1.0",
+            "name": "OG3",
+            "files": [{}],
+            "dependencies": {{ "minecraft": "1.20.1" }}
+        }}"#,
+        files.join(",")
+    )
+}
+
+/// Builds a .mrpack with the index and override `entries`, a `None` entry is written as a symlink
+fn mrpack(index: &str, entries: &[(&str, Option<&str>)]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+
+    writer.start_file("modrinth.index.json", options).unwrap();
+    writer.write_all(index.as_bytes()).unwrap();
+
+    for (name, contents) in entries {
+        match contents {
+            Some(contents) => {
+                writer.start_file(*name, options).unwrap();
+                writer.write_all(contents.as_bytes()).unwrap();
+            }
+            None => writer.add_symlink(*name, "/etc/passwd", options).unwrap(),
+        }
+    }
+
+    writer.finish().unwrap().into_inner()
+}
+
+/// Wraps the .mrpack in the release zip, caches it and plans the update
+fn plan(dir: &Path, mrpack: Vec<u8>) -> Result<UpdatePlan, UpdateError> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file("OG3.mrpack", SimpleFileOptions::default())
+        .unwrap();
+    writer.write_all(&mrpack).unwrap();
+    let zip = writer.finish().unwrap().into_inner();
+    std::fs::write(dir.join("cache").join(ASSET), zip).unwrap();
+
+    let mut config = Config::from_pack_location(dir.join("pack"));
+    config.version = "1.0.0".to_string();
+    let updater = Updater::new(config, dir.join("config.json")).with_cache_dir(dir.join("cache"));
+
+    let release = Release {
+        tag_name: "v1.1".to_string(),
+        assets: vec![Asset {
+            name: ASSET.to_string(),
+            browser_download_url: "https://example.invalid/OG3.zip".to_string(),
+            size: None,
+            hashes: Vec::new(),
+        }],
+        prerelease: false,
+        source: String::new(),
+    };
+    updater.plan(&release, &())
+}
+
+/// Plans an update from a crafted .mrpack and checks it is refused because of `entry`
+fn assert_refused(name: &str, mrpack: Vec<u8>, entry: &str) {
+    let dir = test_dir(name);
+    match plan(&dir, mrpack) {
+        Err(e @ UpdateError::UnsafePath { .. }) => {
+            assert!(
+                e.to_string().contains(entry),
+                "{} does not name {}",
+                e,
+                entry
+            )
+        }
+        Err(e) => panic!("expected an unsafe path error, got {}", e),
+        Ok(_) => panic!("the update was planned"),
+    }
+}
+
+/// An index with [GOOD_FILE] and a copy of it installed at `path`
+fn index_with_path(path: &str) -> String {
+    index_json(&[
+        GOOD_FILE,
+        &GOOD_FILE.replace("mods/good.jar", &path.replace('\\', "\\\\")),
+    ])
+}
+
+#[test]
+fn safe_pack_is_planned() {
+    let dir = test_dir("safe");
+    let mrpack = mrpack(
+        &index_json(&[GOOD_FILE]),
+        &[
+            ("overrides/config/a.toml", Some("a")),
+            ("client-overrides/options.txt", Some("b")),
+        ],
+    );
+
+    let plan = plan(&dir, mrpack).unwrap();
+    assert_eq!(plan.to_download.len(), 1);
+    assert_eq!(plan.to_overwrite.len(), 2);
+}
+
+#[test]
+fn index_path_with_parent_dir_is_refused() {
+    let mrpack = mrpack(&index_with_path("mods/../../evil.jar"), &[]);
+    assert_refused("index-parent", mrpack, "mods/../../evil.jar");
+}
+
+#[test]
+fn absolute_index_path_is_refused() {
+    let mrpack = mrpack(&index_with_path("/tmp/evil.jar"), &[]);
+    assert_refused("index-absolute", mrpack, "/tmp/evil.jar");
+}
+
+#[test]
+fn index_path_with_drive_prefix_is_refused() {
+    let mrpack = mrpack(&index_with_path("C:\\Windows\\evil.dll"), &[]);
+    assert_refused("index-drive", mrpack, "C:\\Windows\\evil.dll");
+}
+
+#[test]
+fn reserved_windows_name_is_refused() {
+    let mrpack = mrpack(&index_with_path("mods/aux.jar"), &[]);
+    assert_refused("index-reserved", mrpack, "mods/aux.jar");
+}
+
+#[test]
+fn override_with_parent_dir_is_refused() {
+    let mrpack = mrpack(
+        &index_json(&[GOOD_FILE]),
+        &[("overrides/../../evil.txt", Some("evil"))],
+    );
+    assert_refused("override-parent", mrpack, "overrides/../../evil.txt");
+}
+
+#[test]
+fn side_override_with_backslashes_is_refused() {
+    let mrpack = mrpack(
+        &index_json(&[GOOD_FILE]),
+        &[("client-overrides\\..\\..\\evil.txt", Some("evil"))],
+    );
+    assert_refused(
+        "override-backslash",
+        mrpack,
+        "client-overrides\\..\\..\\evil.txt",
+    );
+}
+
+#[test]
+fn symlink_override_is_refused() {
+    let mrpack = mrpack(
+        &index_json(&[GOOD_FILE]),
+        &[("overrides/config/passwd", None)],
+    );
+    assert_refused("override-symlink", mrpack, "overrides/config/passwd");
+}
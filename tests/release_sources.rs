@@ -0,0 +1,399 @@
+//! Looks up releases from local mocks of the release sources
+
+mod common;
+
+use common::{serve, serve_json, Reply, TestDir};
+use og3_pack_updater::config::Channel;
+use og3_pack_updater::github::GitHubSource;
+use og3_pack_updater::modrinth::ModrinthSource;
+use og3_pack_updater::source::{ReleaseSource, SourceConfig};
+use og3_pack_updater::{Config, FileHash, UpdateError, Updater};
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Versions of a modpack project as the API lists them, newest first
+const VERSIONS: &str = r#"[
+    {
+        "version_number": "1.3.0",
+        "date_published": "2024-03-01T00:00:00Z",
+        "game_versions": ["1.20.4"],
+        "loaders": ["mrpack"],
+        "mrpack_loaders": ["fabric"],
+        "files": [{ "url": "https://cdn.modrinth.com/1.3.0.mrpack", "filename": "OG3 1.3.0.mrpack", "primary": true, "size": 30, "hashes": { "sha1": "AA" } }]
+    },
+    {
+        "version_number": "1.2.1-forge",
+        "date_published": "2024-02-01T00:00:00Z",
+        "game_versions": ["1.20.1"],
+        "loaders": ["mrpack"],
+        "mrpack_loaders": ["forge"],
+        "files": [{ "url": "https://cdn.modrinth.com/1.2.1.mrpack", "filename": "OG3 1.2.1.mrpack", "primary": true, "size": 20, "hashes": { "sha1": "BB" } }]
+    },
+    {
+        "version_number": "1.2.1-beta",
+        "date_published": "2024-01-15T00:00:00Z",
+        "version_type": "beta",
+        "game_versions": ["1.20.1"],
+        "loaders": ["mrpack"],
+        "mrpack_loaders": ["fabric"],
+        "files": [{ "url": "https://cdn.modrinth.com/1.2.1-beta.mrpack", "filename": "OG3 1.2.1-beta.mrpack", "primary": true, "size": 15, "hashes": { "sha1": "FF" } }]
+    },
+    {
+        "version_number": "1.2.0",
+        "version_type": "release",
+        "date_published": "2024-01-01T00:00:00Z",
+        "game_versions": ["1.20.1"],
+        "loaders": ["mrpack"],
+        "mrpack_loaders": ["fabric"],
+        "files": [
+            { "url": "https://cdn.modrinth.com/changelog.txt", "filename": "changelog.txt", "primary": true, "size": 5, "hashes": {} },
+            { "url": "https://cdn.modrinth.com/1.2.0.mrpack", "filename": "OG3 1.2.0.mrpack", "primary": false, "size": 10, "hashes": { "sha1": "CC", "sha512": "DD" } }
+        ]
+    },
+    {
+        "version_number": "1.1.0",
+        "date_published": "2023-12-01T00:00:00Z",
+        "game_versions": ["1.20.1"],
+        "loaders": ["mrpack"],
+        "mrpack_loaders": ["fabric"],
+        "files": [{ "url": "https://cdn.modrinth.com/1.1.0.mrpack", "filename": "OG3 1.1.0.mrpack", "primary": true, "size": 5, "hashes": { "sha1": "EE" } }]
+    }
+]"#;
+
+/// Releases of a repository as the GitHub API lists them, not in the order they were published
+const GITHUB_RELEASES: &str = r#"[
+    {
+        "tag_name": "v1.2",
+        "draft": false,
+        "prerelease": false,
+        "published_at": "2024-02-01T00:00:00Z",
+        "assets": [{ "name": "OG3.zip", "browser_download_url": "https://github.com/og3/v1.2/OG3.zip", "size": 20 }]
+    },
+    {
+        "tag_name": "v1.4-draft",
+        "draft": true,
+        "prerelease": false,
+        "published_at": "2024-04-01T00:00:00Z",
+        "assets": []
+    },
+    {
+        "tag_name": "v1.3-beta1",
+        "draft": false,
+        "prerelease": true,
+        "published_at": "2024-03-01T00:00:00Z",
+        "assets": [{ "name": "OG3.zip", "browser_download_url": "https://github.com/og3/v1.3-beta1/OG3.zip", "size": 30 }]
+    }
+]"#;
+
+/// A manifest for the next version of the pack
+const MANIFEST: &str = r#"{
+    "version": "1.2.0",
+    "assets": [
+        { "name": "OG3.zip", "url": "https://og3.infy.uk/OG3-1.2.0.zip", "size": 100, "hashes": { "sha1": "AB" } }
+    ]
+}"#;
+
+#[test]
+fn newest_matching_version_is_picked() {
+    let (url, server) = serve_json(VERSIONS);
+    let source = ModrinthSource::new("og3", &format!("{}/v2", url))
+        .with_instance(Some("1.20.1"), Some("fabric"));
+
+    let release = source.latest(&reqwest::blocking::Client::new()).unwrap();
+    let request_line = &server.join().unwrap()[0].line;
+
+    assert!(
+        request_line.starts_with("GET /v2/project/og3/version?game_versions="),
+        "{}",
+        request_line
+    );
+    assert_eq!(release.tag_name, "1.2.0");
+    assert!(!release.prerelease);
+    assert_eq!(release.assets.len(), 1);
+    let asset = release.pack_asset().unwrap();
+    assert_eq!(asset.name, "OG3 1.2.0.mrpack");
+    assert_eq!(
+        asset.browser_download_url,
+        "https://cdn.modrinth.com/1.2.0.mrpack"
+    );
+    assert_eq!(asset.size, Some(10));
+    assert_eq!(
+        asset.hashes,
+        vec![
+            FileHash::Sha512("dd".to_string()),
+            FileHash::Sha1("cc".to_string())
+        ]
+    );
+}
+
+#[test]
+fn beta_channel_picks_pre_releases() {
+    let (url, server) = serve_json(VERSIONS);
+    let source = ModrinthSource::new("og3", &format!("{}/v2", url))
+        .with_instance(Some("1.20.1"), Some("fabric"))
+        .with_channel(Channel::Beta);
+
+    let release = source.latest(&reqwest::blocking::Client::new()).unwrap();
+    server.join().unwrap();
+
+    assert_eq!(release.tag_name, "1.2.1-beta");
+    assert!(release.prerelease);
+}
+
+#[test]
+fn no_matching_version_is_an_error() {
+    let (url, server) = serve_json(VERSIONS);
+    let source = ModrinthSource::new("og3", &format!("{}/v2", url))
+        .with_instance(Some("1.19.2"), Some("quilt"));
+
+    match source.latest(&reqwest::blocking::Client::new()) {
+        Err(e @ UpdateError::Release(_)) => {
+            assert!(e.to_string().contains("1.19.2 quilt"), "{}", e)
+        }
+        Err(e) => panic!("expected a release error, got {}", e),
+        Ok(release) => panic!("{} was picked", release.tag_name),
+    }
+    server.join().unwrap();
+}
+
+#[test]
+fn github_stable_channel_uses_the_latest_release() {
+    let latest = r#"{ "tag_name": "v1.2", "prerelease": false, "assets": [{ "name": "OG3.zip", "browser_download_url": "https://github.com/og3/v1.2/OG3.zip", "size": 20 }] }"#;
+    let (url, server) = serve_json(latest);
+    let source = GitHubSource::new("og3", "pack", &format!("{}/", url));
+
+    let release = source.latest(&reqwest::blocking::Client::new()).unwrap();
+
+    assert_eq!(
+        server.join().unwrap()[0].line,
+        "GET /repos/og3/pack/releases/latest HTTP/1.1"
+    );
+    assert_eq!(release.tag_name, "v1.2");
+    assert_eq!(release.pack_asset().unwrap().size, Some(20));
+}
+
+#[test]
+fn github_beta_channel_picks_the_newest_published_release() {
+    let (url, server) = serve_json(GITHUB_RELEASES);
+    let source = GitHubSource::new("og3", "pack", &url).with_channel(Channel::Beta);
+
+    let release = source.latest(&reqwest::blocking::Client::new()).unwrap();
+
+    assert_eq!(
+        server.join().unwrap()[0].line,
+        "GET /repos/og3/pack/releases HTTP/1.1"
+    );
+    // the draft is newer but not published
+    assert_eq!(release.tag_name, "v1.3-beta1");
+    assert!(release.prerelease);
+}
+
+#[test]
+fn manifest_is_used_when_the_first_source_fails() {
+    let (url, server) = serve_json(MANIFEST);
+    let dir = TestDir::new("release-sources-fallback");
+
+    let mut config = Config::from_pack_location(dir.join("pack"));
+    config.version = "1.1.0".to_string();
+    // nothing listens on port 1, so the first source can not be reached
+    config.source = SourceConfig::Manifest {
+        url: "http://127.0.0.1:1/manifest.json".to_string(),
+    };
+    config.fallback_sources = vec![SourceConfig::Manifest {
+        url: format!("{}/manifest.json", url),
+    }];
+    let updater = Updater::new(config, dir.join("config.json"));
+
+    let check = updater.check().unwrap();
+    server.join().unwrap();
+
+    assert!(check.update_available());
+    assert_eq!(check.installed_version, "1.1.0");
+    assert_eq!(check.release.tag_name, "1.2.0");
+    let asset = check.release.pack_asset().unwrap();
+    assert_eq!(
+        asset.browser_download_url,
+        "https://og3.infy.uk/OG3-1.2.0.zip"
+    );
+    assert_eq!(asset.size, Some(100));
+    assert_eq!(asset.hashes, vec![FileHash::Sha1("ab".to_string())]);
+}
+
+#[test]
+fn every_failed_source_is_reported() {
+    let dir = TestDir::new("release-sources-failed");
+
+    let mut config = Config::from_pack_location(dir.join("pack"));
+    config.source = SourceConfig::Manifest {
+        url: "http://127.0.0.1:1/first.json".to_string(),
+    };
+    config.fallback_sources = vec![SourceConfig::Manifest {
+        url: "http://127.0.0.1:1/second.json".to_string(),
+    }];
+    let updater = Updater::new(config, dir.join("config.json"));
+
+    match updater.check() {
+        Err(e @ UpdateError::Release(_)) => {
+            let message = e.to_string();
+            assert!(message.contains("first.json"), "{}", message);
+            assert!(message.contains("second.json"), "{}", message);
+        }
+        Err(e) => panic!("expected a release error, got {}", e),
+        Ok(check) => panic!("{} was found", check.release.tag_name),
+    }
+}
+
+/// A .mrpack with no files for `version`
+fn empty_mrpack(version: &str) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    writer
+        .start_file("modrinth.index.json", SimpleFileOptions::default())
+        .unwrap();
+    write!(
+        writer,
+        r#"{{ "formatVersion": 1, "game": "minecraft", "versionId": "{}", "name": "OG3", "files": [], "dependencies": {{}} }}"#,
+        version
+    )
+    .unwrap();
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn pack_is_downloaded_from_the_next_source_when_its_download_fails() {
+    let mrpack = empty_mrpack("1.2.0");
+    let manifest = |url: &str, size: usize| {
+        format!(
+            r#"{{ "version": "1.2.0", "assets": [{{ "name": "OG3.mrpack", "url": "{}", "size": {} }}] }}"#,
+            url, size
+        )
+    };
+
+    let size = mrpack.len();
+    let (mirror_files, mirror_files_server) = serve(vec![Reply::ok(mrpack)]);
+    let (mirror, mirror_server) =
+        serve_json(&manifest(&format!("{}/OG3.mrpack", mirror_files), size));
+    // nothing listens on port 1, so the pack of the first source can not be downloaded
+    let broken = manifest("http://127.0.0.1:1/OG3.mrpack", size);
+    let (first, first_server) = serve(vec![Reply::ok(broken.clone()), Reply::ok(broken)]);
+
+    let dir = TestDir::new("release-sources-pack");
+    std::fs::create_dir_all(dir.join("pack")).unwrap();
+    let mut config = Config::from_pack_location(dir.join("pack"));
+    config.version = "1.1.0".to_string();
+    config.source = SourceConfig::Manifest {
+        url: format!("{}/manifest.json", first),
+    };
+    config.fallback_sources = vec![SourceConfig::Manifest {
+        url: format!("{}/manifest.json", mirror),
+    }];
+    let updater = Updater::new(config, dir.join("config.json")).with_cache_dir(dir.join("cache"));
+
+    let check = updater.check().unwrap();
+    let plan = updater.plan(&check.release, &()).unwrap();
+
+    assert_eq!(plan.new_version, "1.2.0");
+    assert_eq!(first_server.join().unwrap().len(), 2);
+    mirror_server.join().unwrap();
+    assert_eq!(
+        mirror_files_server.join().unwrap()[0].line,
+        "GET /OG3.mrpack HTTP/1.1"
+    );
+}
+
+#[test]
+fn installed_release_tag_is_compared_after_an_update() {
+    let mrpack = empty_mrpack("1.3.0-beta.1");
+    let (files, files_server) = serve(vec![Reply::ok(mrpack)]);
+    // the tag can not be worked out from the versionId of the index
+    let manifest = format!(
+        r#"{{ "version": "v1.3-beta1", "assets": [{{ "name": "OG3.mrpack", "url": "{}/OG3.mrpack" }}] }}"#,
+        files
+    );
+    let (url, server) = serve(vec![Reply::ok(manifest.clone()), Reply::ok(manifest)]);
+
+    let dir = TestDir::new("release-sources-tag");
+    std::fs::create_dir_all(dir.join("pack")).unwrap();
+    let mut config = Config::from_pack_location(dir.join("pack"));
+    config.version = "1.2.0".to_string();
+    config.snapshots_to_keep = 0;
+    config.source = SourceConfig::Manifest {
+        url: format!("{}/manifest.json", url),
+    };
+    let mut updater =
+        Updater::new(config, dir.join("config.json")).with_cache_dir(dir.join("cache"));
+
+    let check = updater.check().unwrap();
+    assert!(check.update_available());
+    let plan = updater.plan(&check.release, &()).unwrap();
+    updater.apply(plan, &()).unwrap();
+    files_server.join().unwrap();
+
+    let updater = Updater::from_config_file(dir.join("config.json")).unwrap();
+    assert_eq!(updater.config().version, "1.3.0-beta.1");
+    let check = updater.check().unwrap();
+    assert!(
+        !check.update_available(),
+        "{} is installed",
+        check.installed_version
+    );
+    server.join().unwrap();
+}
+
+/// A folder in the temp dir with a pack folder, and an [Updater] for it checking the manifest at `url`
+fn manifest_updater(name: &str, url: &str) -> (TestDir, Updater) {
+    let dir = TestDir::new(&format!("release-sources-{}", name));
+    std::fs::create_dir_all(dir.join("pack")).unwrap();
+    std::fs::create_dir_all(dir.join("cache")).unwrap();
+    let mut config = Config::from_pack_location(dir.join("pack"));
+    config.version = "1.1.0".to_string();
+    config.source = SourceConfig::Manifest {
+        url: format!("{}/manifest.json", url),
+    };
+    let updater = Updater::new(config, dir.join("config.json")).with_cache_dir(dir.join("cache"));
+    (dir, updater)
+}
+
+#[test]
+fn part_of_another_version_of_the_pack_is_not_resumed() {
+    let mrpack = empty_mrpack("1.2.0");
+    let (files, files_server) = serve(vec![Reply::ok(mrpack.clone())]);
+    let manifest = format!(
+        r#"{{ "version": "v1.2", "assets": [{{ "name": "OG3.mrpack", "url": "{}/OG3.mrpack", "size": {} }}] }}"#,
+        files,
+        mrpack.len()
+    );
+    let (url, server) = serve_json(&manifest);
+    let (dir, updater) = manifest_updater("old-part", &url);
+    // an interrupted download of the last version, with the same asset name
+    std::fs::write(dir.join("cache/OG3.mrpack.v1.1.part"), b"OLDVERSION-").unwrap();
+
+    let check = updater.check().unwrap();
+    let plan = updater.plan(&check.release, &()).unwrap();
+
+    assert_eq!(plan.new_version, "1.2.0");
+    assert_eq!(files_server.join().unwrap()[0].header("Range"), None);
+    assert!(!dir.join("cache/OG3.mrpack.v1.1.part").exists());
+    assert_eq!(std::fs::read(dir.join("cache/OG3.mrpack")).unwrap(), mrpack);
+    server.join().unwrap();
+}
+
+#[test]
+fn unreadable_cached_pack_is_deleted() {
+    let manifest = r#"{ "version": "v1.2", "assets": [{ "name": "OG3.mrpack", "url": "https://example.invalid/OG3.mrpack", "size": 11 }] }"#;
+    let (url, server) = serve_json(manifest);
+    let (dir, updater) = manifest_updater("unreadable", &url);
+    // the right size, but not the pack
+    std::fs::write(dir.join("cache/OG3.mrpack"), b"OLDVERSION-").unwrap();
+
+    let check = updater.check().unwrap();
+    let result = updater.plan(&check.release, &());
+
+    assert!(
+        matches!(result, Err(UpdateError::Archive(_))),
+        "{:?}",
+        result.err()
+    );
+    assert!(!dir.join("cache/OG3.mrpack").exists());
+    server.join().unwrap();
+}